@@ -0,0 +1,60 @@
+//! lexer throughput on multi-megabyte inputs, run with `cargo bench --bench lexer`
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use interpreter::lexer::{self, Lexer};
+
+const PROGRAM: &str = "const limit: int = 1000;
+
+fn main() {
+  var counter: int = 0;
+  var total: float = 0.5;
+  var greeting: string = \"hello world\";
+  var escaped: string = \"tab\\tnewline\\n\";
+  while (counter < limit) { // count up
+    counter = counter + 1;
+    total = total * 1.5 - .25;
+    report(counter, greeting);
+  }
+}
+
+fn report(value: int, message: string) -> bool {
+  return value % 2 == 0 && message != \"\";
+}
+";
+
+/// runs f a few times and returns the fastest run
+fn fastest(mut f: impl FnMut()) -> Duration {
+    (0..10).map(|_| {
+        let start = Instant::now();
+        f();
+        start.elapsed()
+    }).min().unwrap()
+}
+
+fn report(name: &str, bytes: usize, time: Duration) {
+    let mb = bytes as f64 / (1024.0 * 1024.0);
+    println!("{name:<32} {:>8.2} ms {:>8.1} MB/s", time.as_secs_f64() * 1000.0, mb / time.as_secs_f64());
+}
+
+fn main() {
+    for mb in [1, 4, 16] {
+        let input = PROGRAM.repeat(mb * 1024 * 1024 / PROGRAM.len());
+        println!("input: {:.1} MB, {} tokens", input.len() as f64 / (1024.0 * 1024.0), Lexer::new(&input).count());
+        report("Lexer (borrowed tokens)", input.len(), fastest(|| {
+            for token in Lexer::new(black_box(&input)) {
+                black_box(token);
+            }
+        }));
+        report("Lexer + into_owned", input.len(), fastest(|| {
+            for token in Lexer::new(black_box(&input)) {
+                black_box(token.into_owned());
+            }
+        }));
+        report("lex (collected into a Vec)", input.len(), fastest(|| {
+            black_box(lexer::lex(black_box(&input)));
+        }));
+        println!();
+    }
+}
@@ -0,0 +1,25 @@
+//! `cargo fuzz run parse`: lexes and parses arbitrary input, then formats and reparses whatever
+//! parsed. any panic other than a reported "(line) message" error is a crash
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use interpreter::{diagnostic, formatter, lexer, parser};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+    let tree = match diagnostic::catch(|| parser::parse(lexer::lex(input))) {
+        Ok(tree) => tree,
+        Err(error) => {
+            assert!(error.line.is_some(), "panicked without reporting an error: {error}");
+            return;
+        },
+    };
+    let formatted = formatter::format_source(input);
+    let reparsed = parser::parse(lexer::lex(&formatted));
+    assert_eq!(tree.params.len(), reparsed.params.len(), "formatting changed the number of items");
+    assert_eq!(formatter::format_source(&formatted), formatted, "formatting is not idempotent");
+});
@@ -0,0 +1,468 @@
+//! everything the server knows about a single open file
+
+use std::collections::HashMap;
+
+use interpreter::builtins;
+use interpreter::check;
+use interpreter::diagnostic::{self, Diagnostic};
+use interpreter::lexer::{self, Trivia, TriviaToken, TokenType};
+use interpreter::optimize;
+use interpreter::parser::{self, Tree, TreeType};
+use interpreter::symbol::Symbol;
+use interpreter::types;
+
+pub struct Document {
+    pub text: String,
+    pub diagnostics: Vec<Diagnostic>,
+    analysis: Option<Analysis>, // from the last version of the text that parsed
+}
+
+/// the results of lexing and parsing one version of a document
+struct Analysis {
+    text: String,
+    tokens: Vec<TriviaToken>,
+    trailing: Vec<Trivia>,
+    declarations: Vec<Declaration>,
+    by_name: HashMap<Symbol, Vec<usize>>, // indices into declarations of those with each name
+    line_starts: Vec<usize>, // byte offset of the start of every line
+}
+
+pub struct Declaration {
+    pub name: Symbol,
+    pub kind: DeclarationKind,
+    pub name_start: usize, // byte span of the declared name
+    pub name_end: usize,
+    pub start: usize, // byte span of the whole declaration
+    pub end: usize,
+    scope_start: usize, // byte span in which the name can be used
+    scope_end: usize,
+    pub detail: String, // the declaration as written in source, e.g. "var x: int"
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeclarationKind {
+    Function, Const, Var, Parameter, Enum, Struct,
+}
+
+/// a position as the protocol sees it, zero based with the character counted in utf-16 units
+#[derive(Debug, Clone, Copy)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+/// kinds of semantic token, in the order they are listed in the legend
+pub const SEMANTIC_TOKEN_TYPES: [&str; 9] = [
+    "keyword", "type", "function", "variable", "parameter", "number", "string", "operator", "comment",
+];
+
+impl Document {
+    pub fn new(text: String) -> Self {
+        let mut document = Document{
+            text: String::new(),
+            diagnostics: Vec::new(),
+            analysis: None,
+        };
+        document.update(text);
+        document
+    }
+
+    /// replaces the text of the document, reanalysing it
+    /// if the new text does not parse, the previous analysis is kept for symbols and navigation
+    pub fn update(&mut self, text: String) {
+        self.text = text.clone();
+        // the checks after parsing report errors by panicking too, and should any of them panic on
+        // something it does not expect that is reported rather than taking down the server
+        let result = diagnostic::catch(|| {
+            let (tokens, trailing) = lexer::lex_lossless(&text);
+            let tree = parser::parse(tokens.iter().map(|x| x.token.clone()).collect());
+            let mut diagnostics = types::check(&tree);
+            // overflow in constant expressions is only found once the file type checks
+            if diagnostics.is_empty() {
+                diagnostics = optimize::optimize(&mut tree.clone());
+            }
+            diagnostics.extend(check::check(&tree));
+            let declarations = declarations(&tree);
+            (tokens, trailing, diagnostics, declarations)
+        });
+        match result {
+            Ok((tokens, trailing, diagnostics, declarations)) => {
+                self.diagnostics = diagnostics;
+                let mut by_name: HashMap<Symbol, Vec<usize>> = HashMap::new();
+                for (i, declaration) in declarations.iter().enumerate() {
+                    by_name.entry(declaration.name).or_default().push(i);
+                }
+                let line_starts = std::iter::once(0).chain(text.match_indices('\n').map(|(i, _)| i + 1)).collect();
+                self.analysis = Some(Analysis{
+                    text,
+                    tokens,
+                    trailing,
+                    declarations,
+                    by_name,
+                    line_starts,
+                });
+            },
+            Err(error) => self.diagnostics = vec![error],
+        }
+    }
+
+    /// top level functions and variables, in source order
+    pub fn symbols(&self) -> Vec<&Declaration> {
+        match &self.analysis {
+            Some(analysis) => analysis.declarations.iter()
+                .filter(|x| x.scope_start == 0 && x.scope_end == usize::MAX)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// the declaration of the identifier at position, if there is one
+    pub fn definition(&self, position: Position) -> Option<&Declaration> {
+        let analysis = self.analysis.as_ref()?;
+        let offset = offset(&analysis.text, position);
+        analysis.tokens.iter()
+            .filter(|x| x.token.start <= offset && offset <= x.token.end)
+            .find_map(|x| match x.token.value {
+                TokenType::Identifier(name) => analysis.resolve(name, x.token.start),
+                _ => None,
+            })
+    }
+
+    /// converts a byte offset of the text that the current analysis was made from into a position
+    pub fn position(&self, offset: usize) -> Position {
+        match &self.analysis {
+            Some(analysis) => analysis.position(offset),
+            None => position(&self.text, offset),
+        }
+    }
+
+    /// semantic tokens in the relative encoding used by the protocol, five numbers per token
+    pub fn semantic_tokens(&self) -> Vec<usize> {
+        let analysis = match &self.analysis {
+            Some(analysis) => analysis,
+            None => return Vec::new(),
+        };
+        // (start, end, type) of everything that is highlighted, in order
+        let mut spans: Vec<(usize, usize, usize)> = Vec::new();
+        let mut offset = 0;
+        for token in &analysis.tokens {
+            for trivia in &token.leading {
+                if let Trivia::Comment(_) = trivia {
+                    spans.push((offset, offset + trivia.text().len(), 8));
+                }
+                offset += trivia.text().len();
+            }
+            if let Some(kind) = analysis.token_kind(token) {
+                spans.push((token.token.start, token.token.end, kind));
+            }
+            offset = token.token.end;
+        }
+        for trivia in &analysis.trailing {
+            if let Trivia::Comment(_) = trivia {
+                spans.push((offset, offset + trivia.text().len(), 8));
+            }
+            offset += trivia.text().len();
+        }
+
+        let mut data = Vec::with_capacity(spans.len() * 5);
+        let mut prev = Position{ line: 0, character: 0 };
+        for (start, end, kind) in spans {
+            let start_pos = analysis.position(start);
+            let end_pos = analysis.position(end);
+            if start_pos.line != end_pos.line {
+                continue; // tokens spanning lines, i.e. multiline strings, cannot be encoded
+            }
+            let delta_line = start_pos.line - prev.line;
+            let delta_start = match delta_line {
+                0 => start_pos.character - prev.character,
+                _ => start_pos.character,
+            };
+            data.extend([delta_line, delta_start, end_pos.character - start_pos.character, kind, 0]);
+            prev = start_pos;
+        }
+        data
+    }
+}
+
+impl Analysis {
+    /// finds the innermost declaration of name that is visible at offset
+    fn resolve(&self, name: Symbol, offset: usize) -> Option<&Declaration> {
+        self.by_name.get(&name)?.iter()
+            .map(|&i| &self.declarations[i])
+            .filter(|x| x.scope_start <= offset && offset < x.scope_end)
+            .max_by_key(|x| x.scope_start)
+    }
+
+    /// like position, but only looks at the line that offset is on
+    fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.text.len());
+        let line = self.line_starts.partition_point(|&x| x <= offset) - 1;
+        Position{
+            line,
+            character: self.text[self.line_starts[line]..offset].encode_utf16().count(),
+        }
+    }
+
+    /// index into SEMANTIC_TOKEN_TYPES of a token, None for punctuation
+    fn token_kind(&self, token: &TriviaToken) -> Option<usize> {
+        match &token.token.value {
+            TokenType::Const | TokenType::Var | TokenType::Let | TokenType::Enum | TokenType::Struct |
+            TokenType::Function | TokenType::If | TokenType::Else | TokenType::Switch | TokenType::While |
+            TokenType::For | TokenType::In | TokenType::Forever | TokenType::Break | TokenType::Continue |
+            TokenType::Return | TokenType::Defer | TokenType::Try | TokenType::Catch | TokenType::Throw |
+            TokenType::Import | TokenType::Pub | TokenType::BoolLit(_) | TokenType::NoneLit => Some(0),
+            TokenType::Int | TokenType::Float | TokenType::Bool | TokenType::String |
+            TokenType::Void => Some(1),
+            TokenType::IntLit(_) | TokenType::FloatLit(_) => Some(5),
+            TokenType::StringLit(_) => Some(6),
+            TokenType::Identifier(name) => match self.resolve(*name, token.token.start).map(|x| x.kind) {
+                Some(DeclarationKind::Function) => Some(2),
+                Some(DeclarationKind::Parameter) => Some(4),
+                Some(DeclarationKind::Enum | DeclarationKind::Struct) => Some(1),
+                Some(_) => Some(3),
+                None if builtins::get(name.as_str()).is_some() => Some(2),
+                None => Some(1), // anything else that is not declared in this file is assumed to be a type
+            },
+            value if parser::precedence(value).is_some() => Some(7),
+            TokenType::Not | TokenType::BwNot | TokenType::Assign | TokenType::Arrow | TokenType::Question => Some(7),
+            _ => None,
+        }
+    }
+}
+
+/// collects every declaration in a File tree
+fn declarations(tree: &Tree) -> Vec<Declaration> {
+    let mut declarations = Vec::new();
+    for item in &tree.params {
+        let item = match item.value {
+            TreeType::Pub => &item.params[0],
+            _ => item,
+        };
+        match item.value {
+            TreeType::Function => {
+                declarations.push(declaration(item, &item.params[0], DeclarationKind::Function,
+                    (0, usize::MAX), signature(item)));
+                parameter_declarations(item, &mut declarations);
+                body_declarations(&item.params[3], &mut declarations);
+            },
+            TreeType::Assign => {
+                declarations.push(assign_declaration(item, (0, usize::MAX)));
+                lambda_declarations(item, &mut declarations);
+            },
+            TreeType::Enum => {
+                declarations.push(declaration(item, &item.params[0], DeclarationKind::Enum,
+                    (0, usize::MAX), format!("enum {}", leaf_text(&item.params[0]))));
+            },
+            TreeType::Struct => {
+                let type_params: Vec<String> = item.params[1].params.iter().map(leaf_text).collect();
+                let detail = match type_params.is_empty() {
+                    true => format!("struct {}", leaf_text(&item.params[0])),
+                    false => format!("struct {}<{}>", leaf_text(&item.params[0]), type_params.join(", ")),
+                };
+                declarations.push(declaration(item, &item.params[0], DeclarationKind::Struct, (0, usize::MAX), detail));
+            },
+            _ => {},
+        }
+    }
+    declarations
+}
+
+fn body_declarations(body: &Tree, declarations: &mut Vec<Declaration>) {
+    for statement in &body.params {
+        statement_declarations(statement, body, declarations);
+    }
+}
+
+fn statement_declarations(statement: &Tree, body: &Tree, declarations: &mut Vec<Declaration>) {
+    match statement.value {
+        // locals can be used from their declaration up to the end of the enclosing body
+        TreeType::Assign => {
+            declarations.push(assign_declaration(statement, (statement.start, body.end)));
+            lambda_declarations(statement, declarations);
+        },
+        TreeType::Reassign | TreeType::Expression | TreeType::Return | TreeType::Throw => lambda_declarations(statement, declarations),
+        TreeType::While => body_declarations(&statement.params[1], declarations),
+        TreeType::Forever => body_declarations(&statement.params[0], declarations),
+        TreeType::Try => {
+            // the caught error can be used anywhere in the catch body
+            body_declarations(&statement.params[0], declarations);
+            let catch = &statement.params[2];
+            declarations.push(declaration(statement, &statement.params[1], DeclarationKind::Const,
+                (catch.start, catch.end), format!("const {}: Error", leaf_text(&statement.params[1]))));
+            body_declarations(catch, declarations);
+        },
+        TreeType::For => {
+            // the loop variable can be used anywhere in the loop's body
+            let loop_body = &statement.params[2];
+            declarations.push(declaration(statement, &statement.params[0], DeclarationKind::Const,
+                (loop_body.start, loop_body.end), format!("const {}", leaf_text(&statement.params[0]))));
+            body_declarations(loop_body, declarations);
+        },
+        TreeType::Label | TreeType::Defer => statement_declarations(statement.params.last().unwrap(), body, declarations),
+        TreeType::Switch => {
+            for arm in &statement.params[1..] {
+                // names bound by patterns can be used in the guard and body of their arm
+                for pattern in &arm.params[0].params {
+                    pattern_declarations(pattern, arm, declarations);
+                }
+                body_declarations(&arm.params[1], declarations);
+            }
+        },
+        TreeType::If => {
+            // the name an `if let` binds can be used in the body it guards
+            if statement.params[0].value == TreeType::Let {
+                let guarded = &statement.params[1];
+                declarations.push(declaration(&statement.params[0], &statement.params[0].params[0], DeclarationKind::Const,
+                    (guarded.start, guarded.end), format!("const {}", leaf_text(&statement.params[0].params[0]))));
+            }
+            body_declarations(&statement.params[1], declarations);
+            match statement.params.get(2) {
+                Some(other) if other.value == TreeType::If => statement_declarations(other, body, declarations),
+                Some(other) => body_declarations(other, declarations),
+                None => {},
+            }
+        },
+        _ => {},
+    }
+}
+
+/// declares the parameters of a function or lambda, which can be used anywhere inside of it
+fn parameter_declarations(function: &Tree, declarations: &mut Vec<Declaration>) {
+    let params = match function.value {
+        TreeType::Function => &function.params[1],
+        _ => &function.params[0],
+    };
+    for param in &params.params {
+        declarations.push(declaration(param, &param.params[0], DeclarationKind::Parameter,
+            (function.start, function.end), format!("{}: {}", leaf_text(&param.params[0]), type_text(&param.params[1]))));
+    }
+}
+
+/// collects the declarations inside of every lambda in an expression, or in a statement's expressions
+fn lambda_declarations(tree: &Tree, declarations: &mut Vec<Declaration>) {
+    if tree.value == TreeType::Lambda {
+        parameter_declarations(tree, declarations);
+        body_declarations(&tree.params[2], declarations);
+        return;
+    }
+    for param in &tree.params {
+        lambda_declarations(param, declarations);
+    }
+}
+
+fn pattern_declarations(pattern: &Tree, arm: &Tree, declarations: &mut Vec<Declaration>) {
+    match &pattern.value {
+        TreeType::Leaf(TokenType::Identifier(name)) if name.as_str() != "_" => {
+            declarations.push(declaration(pattern, pattern, DeclarationKind::Const,
+                (arm.start, arm.end), format!("const {name}")));
+        },
+        TreeType::VariantPattern => {
+            for field in &pattern.params[2..] {
+                pattern_declarations(field, arm, declarations);
+            }
+        },
+        _ => {},
+    }
+}
+
+fn assign_declaration(tree: &Tree, scope: (usize, usize)) -> Declaration {
+    let kind = match tree.params[0].value {
+        TreeType::Leaf(TokenType::Const) => DeclarationKind::Const,
+        _ => DeclarationKind::Var,
+    };
+    let mut detail = format!("{} {}", leaf_text(&tree.params[0]), leaf_text(&tree.params[1]));
+    if tree.params[2].value != TreeType::Inferred {
+        detail.push_str(&format!(": {}", type_text(&tree.params[2])));
+    }
+    declaration(tree, &tree.params[1], kind, scope, detail)
+}
+
+fn declaration(tree: &Tree, name: &Tree, kind: DeclarationKind, scope: (usize, usize), detail: String) -> Declaration {
+    Declaration{
+        name: match name.value {
+            TreeType::Leaf(TokenType::Identifier(name)) => name,
+            _ => panic!("({}) expected declared name to be an identifier", name.line),
+        },
+        kind,
+        name_start: name.start,
+        name_end: name.end,
+        start: tree.start,
+        end: tree.end,
+        scope_start: scope.0,
+        scope_end: scope.1,
+        detail,
+    }
+}
+
+/// a function's signature as it would be written in source
+fn signature(function: &Tree) -> String {
+    let params: Vec<String> = function.params[1].params.iter()
+        .map(|x| format!("{}: {}", leaf_text(&x.params[0]), type_text(&x.params[1])))
+        .collect();
+    let type_params = match function.params.get(4) {
+        Some(type_params) => {
+            let names: Vec<String> = type_params.params.iter().map(leaf_text).collect();
+            format!("<{}>", names.join(", "))
+        },
+        None => String::new(),
+    };
+    let mut signature = format!("fn {}{type_params}({})", leaf_text(&function.params[0]), params.join(", "));
+    if !matches!(function.params[2].value, TreeType::Leaf(TokenType::Void)) {
+        signature.push_str(&format!(" -> {}", type_text(&function.params[2])));
+    }
+    signature
+}
+
+/// a type as it would be written in source
+fn type_text(tree: &Tree) -> String {
+    match &tree.value {
+        TreeType::FunctionType => {
+            let (ret_type, params) = tree.params.split_last().unwrap();
+            let params: Vec<String> = params.iter().map(type_text).collect();
+            match ret_type.value {
+                TreeType::Leaf(TokenType::Void) => format!("fn({})", params.join(", ")),
+                _ => format!("fn({}) -> {}", params.join(", "), type_text(ret_type)),
+            }
+        },
+        TreeType::GenericType => {
+            let args: Vec<String> = tree.params[1..].iter().map(type_text).collect();
+            format!("{}<{}>", leaf_text(&tree.params[0]), args.join(", "))
+        },
+        TreeType::OptionalType => format!("{}?", type_text(&tree.params[0])),
+        _ => leaf_text(tree),
+    }
+}
+
+fn leaf_text(tree: &Tree) -> String {
+    match &tree.value {
+        TreeType::Leaf(token) => token.to_string(),
+        other => format!("{other:?}"),
+    }
+}
+
+pub fn position(text: &str, offset: usize) -> Position {
+    let offset = offset.min(text.len());
+    let line_start = text[..offset].rfind('\n').map_or(0, |x| x + 1);
+    Position{
+        line: text[..offset].matches('\n').count(),
+        character: text[line_start..offset].encode_utf16().count(),
+    }
+}
+
+pub fn offset(text: &str, position: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match text[line_start..].find('\n') {
+            Some(newline) => line_start += newline + 1,
+            None => return text.len(),
+        }
+    }
+    let mut character = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if character >= position.character || c == '\n' {
+            return line_start + i;
+        }
+        character += c.len_utf16();
+    }
+    text.len()
+}
@@ -0,0 +1,256 @@
+//! just enough json for the language server protocol
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>), // keeps the order keys were written in
+}
+
+impl Json {
+    /// looks up key in an object, None if this is not an object or the key is missing
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            Json::Number(num) if *num >= 0.0 && num.fract() == 0.0 => Some(*num as usize),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Json>> {
+        match self {
+            Json::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+}
+
+/// builds a json object out of key value pairs
+pub fn object(fields: Vec<(&str, Json)>) -> Json {
+    Json::Object(fields.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+}
+
+impl From<&str> for Json {
+    fn from(string: &str) -> Self {
+        Json::String(string.to_string())
+    }
+}
+impl From<String> for Json {
+    fn from(string: String) -> Self {
+        Json::String(string)
+    }
+}
+impl From<usize> for Json {
+    fn from(num: usize) -> Self {
+        Json::Number(num as f64)
+    }
+}
+impl From<bool> for Json {
+    fn from(b: bool) -> Self {
+        Json::Bool(b)
+    }
+}
+
+impl std::fmt::Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{b}"),
+            Json::Number(num) => {
+                if num.fract() == 0.0 && num.abs() < 1e15 {
+                    write!(f, "{}", *num as i64)
+                }
+                else {
+                    write!(f, "{num}")
+                }
+            },
+            Json::String(string) => write_string(f, string),
+            Json::Array(array) => {
+                write!(f, "[")?;
+                for (i, value) in array.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                write!(f, "]")
+            },
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            },
+        }
+    }
+}
+
+fn write_string(f: &mut std::fmt::Formatter<'_>, string: &str) -> std::fmt::Result {
+    write!(f, "\"")?;
+    for c in string.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// parses a single json value, the whole input must be used
+pub fn parse(input: &str) -> Result<Json, String> {
+    let mut chars = input.chars().peekable();
+    let value = parse_value(&mut chars)?;
+    skip_whitespace(&mut chars);
+    match chars.next() {
+        Some(c) => Err(format!("unexpected '{c}' after json value")),
+        None => Ok(value),
+    }
+}
+
+fn skip_whitespace(chars: &mut std::iter::Peekable<std::str::Chars>) {
+    while chars.peek().is_some_and(|x| x.is_whitespace()) {
+        chars.next();
+    }
+}
+
+fn parse_value(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<Json, String> {
+    skip_whitespace(chars);
+    match chars.peek() {
+        None => Err(String::from("unexpected end of json")),
+        Some('n') => parse_word(chars, "null", Json::Null),
+        Some('t') => parse_word(chars, "true", Json::Bool(true)),
+        Some('f') => parse_word(chars, "false", Json::Bool(false)),
+        Some('"') => Ok(Json::String(parse_string(chars)?)),
+        Some('[') => {
+            chars.next();
+            let mut array = Vec::new();
+            skip_whitespace(chars);
+            if chars.peek() == Some(&']') {
+                chars.next();
+                return Ok(Json::Array(array));
+            }
+            loop {
+                array.push(parse_value(chars)?);
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some(']') => return Ok(Json::Array(array)),
+                    _ => return Err(String::from("expected ',' or ']' in json array")),
+                }
+            }
+        },
+        Some('{') => {
+            chars.next();
+            let mut fields = Vec::new();
+            skip_whitespace(chars);
+            if chars.peek() == Some(&'}') {
+                chars.next();
+                return Ok(Json::Object(fields));
+            }
+            loop {
+                skip_whitespace(chars);
+                let key = parse_string(chars)?;
+                skip_whitespace(chars);
+                if chars.next() != Some(':') {
+                    return Err(String::from("expected ':' in json object"));
+                }
+                fields.push((key, parse_value(chars)?));
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some('}') => return Ok(Json::Object(fields)),
+                    _ => return Err(String::from("expected ',' or '}' in json object")),
+                }
+            }
+        },
+        Some(_) => {
+            let mut word = String::new();
+            while chars.peek().is_some_and(|x| x.is_ascii_digit() || "+-.eE".contains(*x)) {
+                word.push(chars.next().unwrap());
+            }
+            word.parse().map(Json::Number).map_err(|_| format!("invalid json number: '{word}'"))
+        },
+    }
+}
+
+fn parse_word(chars: &mut std::iter::Peekable<std::str::Chars>, word: &str, value: Json) -> Result<Json, String> {
+    for expected in word.chars() {
+        if chars.next() != Some(expected) {
+            return Err(format!("expected '{word}'"));
+        }
+    }
+    Ok(value)
+}
+
+fn parse_string(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<String, String> {
+    if chars.next() != Some('"') {
+        return Err(String::from("expected json string"));
+    }
+    let mut string = String::new();
+    loop {
+        match chars.next() {
+            None => return Err(String::from("unterminated json string")),
+            Some('"') => return Ok(string),
+            Some('\\') => match chars.next() {
+                Some('"') => string.push('"'),
+                Some('\\') => string.push('\\'),
+                Some('/') => string.push('/'),
+                Some('b') => string.push('\u{8}'),
+                Some('f') => string.push('\u{c}'),
+                Some('n') => string.push('\n'),
+                Some('r') => string.push('\r'),
+                Some('t') => string.push('\t'),
+                Some('u') => {
+                    let mut code = parse_hex(chars)?;
+                    // surrogate pairs encode chars outside of the basic multilingual plane
+                    if (0xd800..0xdc00).contains(&code) {
+                        if chars.next() != Some('\\') || chars.next() != Some('u') {
+                            return Err(String::from("unpaired surrogate in json string"));
+                        }
+                        let low = parse_hex(chars)?;
+                        code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+                    }
+                    string.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                },
+                _ => return Err(String::from("invalid escape in json string")),
+            },
+            Some(c) => string.push(c),
+        }
+    }
+}
+
+fn parse_hex(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<u32, String> {
+    let mut code = 0;
+    for _ in 0..4 {
+        let digit = chars.next().and_then(|x| x.to_digit(16))
+            .ok_or_else(|| String::from("invalid unicode escape in json string"))?;
+        code = code * 16 + digit;
+    }
+    Ok(code)
+}
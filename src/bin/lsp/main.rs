@@ -0,0 +1,257 @@
+//! language server, speaks the language server protocol over stdin and stdout
+
+mod document;
+mod json;
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+use interpreter::diagnostic::Severity;
+
+use document::{DeclarationKind, Document, Position};
+use json::{Json, object};
+
+fn main() {
+    let stdin = std::io::stdin();
+    let mut input = stdin.lock();
+    let mut output = std::io::stdout().lock();
+    let mut server = Server{
+        documents: HashMap::new(),
+        shutdown: false,
+    };
+    while let Some(message) = read_message(&mut input) {
+        let message = match json::parse(&message) {
+            Ok(message) => message,
+            Err(error) => {
+                send(&mut output, &object(vec![
+                    ("jsonrpc", "2.0".into()),
+                    ("id", Json::Null),
+                    ("error", object(vec![("code", Json::Number(-32700.0)), ("message", error.into())])),
+                ]));
+                continue;
+            },
+        };
+        let method = message.get("method").and_then(Json::as_str).unwrap_or_default();
+        let params = message.get("params").cloned().unwrap_or(Json::Null);
+        match message.get("id") {
+            // requests get exactly one response
+            Some(id) => {
+                let response = match server.request(method, &params) {
+                    Ok(result) => object(vec![("jsonrpc", "2.0".into()), ("id", id.clone()), ("result", result)]),
+                    Err((code, error)) => object(vec![
+                        ("jsonrpc", "2.0".into()),
+                        ("id", id.clone()),
+                        ("error", object(vec![("code", Json::Number(code as f64)), ("message", error.into())])),
+                    ]),
+                };
+                send(&mut output, &response);
+            },
+            // notifications get zero or more notifications back
+            None => {
+                if method == "exit" {
+                    std::process::exit(if server.shutdown { 0 } else { 1 });
+                }
+                for notification in server.notification(method, &params) {
+                    send(&mut output, &notification);
+                }
+            },
+        }
+    }
+}
+
+struct Server {
+    documents: HashMap<String, Document>, // keyed by uri
+    shutdown: bool, // whether a shutdown request has been received
+}
+
+impl Server {
+    fn request(&mut self, method: &str, params: &Json) -> Result<Json, (i64, String)> {
+        match method {
+            "initialize" => Ok(object(vec![
+                ("capabilities", object(vec![
+                    ("textDocumentSync", 1.into()), // the full text is sent on every change
+                    ("documentSymbolProvider", true.into()),
+                    ("definitionProvider", true.into()),
+                    ("hoverProvider", true.into()),
+                    ("semanticTokensProvider", object(vec![
+                        ("legend", object(vec![
+                            ("tokenTypes", Json::Array(document::SEMANTIC_TOKEN_TYPES.iter().map(|x| (*x).into()).collect())),
+                            ("tokenModifiers", Json::Array(Vec::new())),
+                        ])),
+                        ("full", true.into()),
+                    ])),
+                ])),
+                ("serverInfo", object(vec![("name", "interpreter-lsp".into())])),
+            ])),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Json::Null)
+            },
+            "textDocument/documentSymbol" => {
+                let (_, document) = self.document(params)?;
+                let symbols = document.symbols().iter().map(|x| object(vec![
+                    ("name", x.name.as_str().into()),
+                    ("detail", x.detail.clone().into()),
+                    ("kind", match x.kind {
+                        DeclarationKind::Function => 12.into(),
+                        DeclarationKind::Const => 14.into(),
+                        DeclarationKind::Enum => 10.into(),
+                        DeclarationKind::Struct => 23.into(),
+                        _ => 13.into(),
+                    }),
+                    ("range", range(document, x.start, x.end)),
+                    ("selectionRange", range(document, x.name_start, x.name_end)),
+                ])).collect();
+                Ok(Json::Array(symbols))
+            },
+            "textDocument/definition" => {
+                let (uri, document) = self.document(params)?;
+                match document.definition(position(params)?) {
+                    Some(declaration) => Ok(object(vec![
+                        ("uri", uri.into()),
+                        ("range", range(document, declaration.name_start, declaration.name_end)),
+                    ])),
+                    None => Ok(Json::Null),
+                }
+            },
+            "textDocument/hover" => {
+                let (_, document) = self.document(params)?;
+                match document.definition(position(params)?) {
+                    Some(declaration) => Ok(object(vec![
+                        ("contents", object(vec![
+                            ("kind", "markdown".into()),
+                            ("value", format!("```\n{}\n```", declaration.detail).into()),
+                        ])),
+                    ])),
+                    None => Ok(Json::Null),
+                }
+            },
+            "textDocument/semanticTokens/full" => {
+                let (_, document) = self.document(params)?;
+                let data = document.semantic_tokens().into_iter().map(Json::from).collect();
+                Ok(object(vec![("data", Json::Array(data))]))
+            },
+            _ => Err((-32601, format!("unhandled method: {method}"))),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        let uri = params.get("textDocument").and_then(|x| x.get("uri")).and_then(Json::as_str)
+            .unwrap_or_default().to_string();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params.get("textDocument").and_then(|x| x.get("text")).and_then(Json::as_str)
+                    .unwrap_or_default();
+                self.documents.insert(uri.clone(), Document::new(text.to_string()));
+                vec![self.diagnostics(&uri)]
+            },
+            "textDocument/didChange" => {
+                // with full sync the last change holds the whole text
+                let text = params.get("contentChanges").and_then(Json::as_array)
+                    .and_then(|x| x.last()).and_then(|x| x.get("text")).and_then(Json::as_str);
+                match (self.documents.get_mut(&uri), text) {
+                    (Some(document), Some(text)) => {
+                        document.update(text.to_string());
+                        vec![self.diagnostics(&uri)]
+                    },
+                    _ => Vec::new(),
+                }
+            },
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                // clear out whatever diagnostics were shown for the document
+                vec![object(vec![
+                    ("jsonrpc", "2.0".into()),
+                    ("method", "textDocument/publishDiagnostics".into()),
+                    ("params", object(vec![("uri", uri.into()), ("diagnostics", Json::Array(Vec::new()))])),
+                ])]
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    /// the document named by params.textDocument.uri
+    fn document(&self, params: &Json) -> Result<(String, &Document), (i64, String)> {
+        let uri = params.get("textDocument").and_then(|x| x.get("uri")).and_then(Json::as_str)
+            .ok_or((-32602, String::from("missing textDocument.uri")))?;
+        match self.documents.get(uri) {
+            Some(document) => Ok((uri.to_string(), document)),
+            None => Err((-32602, format!("document is not open: {uri}"))),
+        }
+    }
+
+    /// a publishDiagnostics notification for the document at uri
+    fn diagnostics(&self, uri: &str) -> Json {
+        let document = &self.documents[uri];
+        let diagnostics = document.diagnostics.iter().map(|x| {
+            // errors only know their line, so the whole line is marked
+            let line = x.line.unwrap_or(1).saturating_sub(1);
+            let line_text = document.text.lines().nth(line).unwrap_or_default();
+            object(vec![
+                ("range", object(vec![
+                    ("start", object(vec![("line", line.into()), ("character", 0.into())])),
+                    ("end", object(vec![("line", line.into()), ("character", line_text.encode_utf16().count().into())])),
+                ])),
+                ("severity", match x.severity {
+                    Severity::Error => 1.into(),
+                    Severity::Warning => 2.into(),
+                }),
+                ("source", "interpreter".into()),
+                ("message", x.message.clone().into()),
+            ])
+        }).collect();
+        object(vec![
+            ("jsonrpc", "2.0".into()),
+            ("method", "textDocument/publishDiagnostics".into()),
+            ("params", object(vec![("uri", uri.into()), ("diagnostics", Json::Array(diagnostics))])),
+        ])
+    }
+}
+
+/// params.position of a request
+fn position(params: &Json) -> Result<Position, (i64, String)> {
+    let position = params.get("position");
+    let line = position.and_then(|x| x.get("line")).and_then(Json::as_usize);
+    let character = position.and_then(|x| x.get("character")).and_then(Json::as_usize);
+    match (line, character) {
+        (Some(line), Some(character)) => Ok(Position{ line, character }),
+        _ => Err((-32602, String::from("missing position"))),
+    }
+}
+
+fn range(document: &Document, start: usize, end: usize) -> Json {
+    let start = document.position(start);
+    let end = document.position(end);
+    object(vec![
+        ("start", object(vec![("line", start.line.into()), ("character", start.character.into())])),
+        ("end", object(vec![("line", end.line.into()), ("character", end.character.into())])),
+    ])
+}
+
+/// reads one message body, None once the input is closed
+fn read_message(input: &mut impl BufRead) -> Option<String> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header).ok()? == 0 {
+            return None;
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length") {
+            length = value.trim().parse().ok();
+        }
+    }
+    let mut body = vec![0; length?];
+    input.read_exact(&mut body).ok()?;
+    String::from_utf8(body).ok()
+}
+
+fn send(output: &mut impl Write, message: &Json) {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len()).expect("could not write to stdout");
+    output.flush().expect("could not write to stdout");
+}
@@ -0,0 +1,304 @@
+//! functions that every script can call without declaring them, implemented natively
+//!
+//! a call to a name that no function in the script declares is looked up here, so a script can
+//! replace any of these by declaring a function with the same name
+
+use std::cmp::Ordering;
+use std::io::{BufRead, Write};
+
+use crate::value::{OVERFLOW, Value};
+
+/// where input reads from and print writes to, stdin and stdout unless the embedder says otherwise
+pub struct Io {
+    pub input: Box<dyn BufRead + Send>,
+    pub output: Box<dyn Write + Send>,
+}
+
+impl Io {
+    pub fn stdio() -> Self {
+        Io{
+            input: Box::new(Stdin(Vec::new())),
+            output: Box::new(std::io::stdout()),
+        }
+    }
+}
+
+/// stdin, locked only while it is being read so that any number of Io can share it
+struct Stdin(Vec<u8>); // what fill_buf last saw, stdin's own buffer is where it is really kept
+
+impl std::io::Read for Stdin {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        std::io::stdin().read(buf)
+    }
+}
+
+impl BufRead for Stdin {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.0 = std::io::stdin().lock().fill_buf()?.to_vec();
+        Ok(&self.0)
+    }
+
+    fn consume(&mut self, amount: usize) {
+        std::io::stdin().lock().consume(amount);
+        self.0.clear();
+    }
+
+    fn read_line(&mut self, buf: &mut String) -> std::io::Result<usize> {
+        std::io::stdin().read_line(buf)
+    }
+}
+
+/// why a native function did not return a value
+#[derive(Debug, Clone)]
+pub enum Stop {
+    Error(String), // a message without a line, the caller attaches the line of the call
+    Exit(i32), // the whole script should stop with this exit code
+}
+
+impl From<String> for Stop {
+    fn from(message: String) -> Self {
+        Stop::Error(message)
+    }
+}
+impl From<&str> for Stop {
+    fn from(message: &str) -> Self {
+        Stop::Error(message.to_string())
+    }
+}
+
+pub type NativeFn = fn(&mut Io, Vec<Value>) -> Result<Value, Stop>;
+
+pub struct Builtin {
+    pub name: &'static str,
+    pub min_args: usize,
+    pub max_args: Option<usize>, // None if any number of arguments is accepted
+    pub function: NativeFn,
+}
+
+pub const BUILTINS: [Builtin; 21] = [
+    Builtin{ name: "print", min_args: 0, max_args: None, function: print },
+    Builtin{ name: "println", min_args: 0, max_args: None, function: println },
+    Builtin{ name: "input", min_args: 0, max_args: Some(1), function: input },
+    Builtin{ name: "len", min_args: 1, max_args: Some(1), function: len },
+    Builtin{ name: "to_string", min_args: 1, max_args: Some(1), function: to_string },
+    Builtin{ name: "parse_int", min_args: 1, max_args: Some(1), function: parse_int },
+    Builtin{ name: "parse_float", min_args: 1, max_args: Some(1), function: parse_float },
+    Builtin{ name: "abs", min_args: 1, max_args: Some(1), function: abs },
+    Builtin{ name: "min", min_args: 1, max_args: None, function: min },
+    Builtin{ name: "max", min_args: 1, max_args: None, function: max },
+    Builtin{ name: "sqrt", min_args: 1, max_args: Some(1), function: sqrt },
+    Builtin{ name: "wrapping_add", min_args: 2, max_args: Some(2), function: wrapping_add },
+    Builtin{ name: "wrapping_sub", min_args: 2, max_args: Some(2), function: wrapping_sub },
+    Builtin{ name: "wrapping_mul", min_args: 2, max_args: Some(2), function: wrapping_mul },
+    Builtin{ name: "wrapping_div", min_args: 2, max_args: Some(2), function: wrapping_div },
+    Builtin{ name: "wrapping_rem", min_args: 2, max_args: Some(2), function: wrapping_rem },
+    Builtin{ name: "saturating_add", min_args: 2, max_args: Some(2), function: saturating_add },
+    Builtin{ name: "saturating_sub", min_args: 2, max_args: Some(2), function: saturating_sub },
+    Builtin{ name: "saturating_mul", min_args: 2, max_args: Some(2), function: saturating_mul },
+    Builtin{ name: "saturating_div", min_args: 2, max_args: Some(2), function: saturating_div },
+    Builtin{ name: "exit", min_args: 0, max_args: Some(1), function: exit },
+];
+
+/// the builtin called name, if there is one
+pub fn get(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|x| x.name == name)
+}
+
+impl Builtin {
+    /// checks the number of arguments, then calls the function
+    pub fn call(&self, io: &mut Io, args: Vec<Value>) -> Result<Value, Stop> {
+        if args.len() < self.min_args || self.max_args.is_some_and(|x| args.len() > x) {
+            let expected = match self.max_args {
+                Some(max) if max == self.min_args => max.to_string(),
+                Some(max) => format!("{} to {max}", self.min_args),
+                None => format!("at least {}", self.min_args),
+            };
+            return Err(Stop::Error(format!("'{}' takes {expected} arguments but {} were given", self.name, args.len())));
+        }
+        (self.function)(io, args)
+    }
+}
+
+fn write_error(_: std::io::Error) -> Stop {
+    Stop::Error(String::from("could not write output"))
+}
+
+/// writes every argument, separated by spaces
+fn print(io: &mut Io, args: Vec<Value>) -> Result<Value, Stop> {
+    let text: Vec<String> = args.iter().map(Value::to_string).collect();
+    write!(io.output, "{}", text.join(" ")).map_err(write_error)?;
+    Ok(Value::Void)
+}
+
+/// print followed by a newline
+fn println(io: &mut Io, args: Vec<Value>) -> Result<Value, Stop> {
+    print(io, args)?;
+    writeln!(io.output).map_err(write_error)?;
+    Ok(Value::Void)
+}
+
+/// prints the prompt if there is one, then reads a line without its line ending
+/// returns an empty string once the input has ended
+fn input(io: &mut Io, args: Vec<Value>) -> Result<Value, Stop> {
+    print(io, args)?;
+    io.output.flush().map_err(write_error)?;
+    let mut line = String::new();
+    io.input.read_line(&mut line).map_err(|_| "could not read input")?;
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(Value::String(line))
+}
+
+/// the number of characters in a string or elements in an array
+fn len(_: &mut Io, args: Vec<Value>) -> Result<Value, Stop> {
+    match &args[0] {
+        Value::String(string) => Ok(Value::Int(string.chars().count() as i64)),
+        Value::Array(array) => Ok(Value::Int(array.len() as i64)),
+        other => Err(format!("'len' expects a string or an array, found {}", other.type_name()).into()),
+    }
+}
+
+fn to_string(_: &mut Io, args: Vec<Value>) -> Result<Value, Stop> {
+    Ok(Value::String(args[0].to_string()))
+}
+
+fn parse_int(_: &mut Io, args: Vec<Value>) -> Result<Value, Stop> {
+    match &args[0] {
+        Value::String(string) => match string.trim().parse() {
+            Ok(int) => Ok(Value::Int(int)),
+            Err(_) => Err(format!("invalid int: \"{string}\"").into()),
+        },
+        other => Err(format!("'parse_int' expects a string, found {}", other.type_name()).into()),
+    }
+}
+
+fn parse_float(_: &mut Io, args: Vec<Value>) -> Result<Value, Stop> {
+    match &args[0] {
+        Value::String(string) => match string.trim().parse() {
+            Ok(float) => Ok(Value::Float(float)),
+            Err(_) => Err(format!("invalid float: \"{string}\"").into()),
+        },
+        other => Err(format!("'parse_float' expects a string, found {}", other.type_name()).into()),
+    }
+}
+
+fn abs(_: &mut Io, args: Vec<Value>) -> Result<Value, Stop> {
+    match &args[0] {
+        Value::Int(int) => Ok(Value::Int(int.checked_abs().ok_or(OVERFLOW)?)),
+        Value::Float(float) => Ok(Value::Float(float.abs())),
+        other => Err(format!("'abs' expects an int or a float, found {}", other.type_name()).into()),
+    }
+}
+
+/// the smallest (or with want_greater, the largest) of a list of ints, floats or strings
+fn extreme(name: &str, args: Vec<Value>, want_greater: bool) -> Result<Value, Stop> {
+    let mut args = args.into_iter();
+    let mut best = args.next().unwrap();
+    if !matches!(best, Value::Int(_) | Value::Float(_) | Value::String(_)) {
+        return Err(format!("'{name}' expects ints, floats or strings, found {}", best.type_name()).into());
+    }
+    for arg in args {
+        if arg.type_name() != best.type_name() {
+            return Err(format!("'{name}' expects arguments of the same type, found {} and {}",
+                best.type_name(), arg.type_name()).into());
+        }
+        let ordering = match (&arg, &best) {
+            (Value::Int(a), Value::Int(b)) => a.partial_cmp(b),
+            (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+            (Value::String(a), Value::String(b)) => a.partial_cmp(b),
+            _ => None,
+        };
+        if ordering == Some(if want_greater { Ordering::Greater } else { Ordering::Less }) {
+            best = arg;
+        }
+    }
+    Ok(best)
+}
+
+fn min(_: &mut Io, args: Vec<Value>) -> Result<Value, Stop> {
+    extreme("min", args, false)
+}
+
+fn max(_: &mut Io, args: Vec<Value>) -> Result<Value, Stop> {
+    extreme("max", args, true)
+}
+
+/// the square root of a float (or int) as a float
+fn sqrt(_: &mut Io, args: Vec<Value>) -> Result<Value, Stop> {
+    match &args[0] {
+        Value::Int(int) => Ok(Value::Float((*int as f64).sqrt())),
+        Value::Float(float) => Ok(Value::Float(float.sqrt())),
+        other => Err(format!("'sqrt' expects an int or a float, found {}", other.type_name()).into()),
+    }
+}
+
+/// the two int arguments of an arithmetic builtin, the second of which is a divisor if divides
+fn ints(name: &str, args: &[Value], divides: bool) -> Result<(i64, i64), Stop> {
+    match args {
+        [Value::Int(_), Value::Int(0)] if divides => Err("division by zero".into()),
+        [Value::Int(a), Value::Int(b)] => Ok((*a, *b)),
+        [a, b] => Err(format!("'{name}' expects two ints, found {} and {}", a.type_name(), b.type_name()).into()),
+        _ => unreachable!("the number of arguments is checked before calling"),
+    }
+}
+
+// arithmetic that wraps around instead of overflowing, e.g. the largest int plus one is the smallest
+fn wrapping_add(_: &mut Io, args: Vec<Value>) -> Result<Value, Stop> {
+    let (a, b) = ints("wrapping_add", &args, false)?;
+    Ok(Value::Int(a.wrapping_add(b)))
+}
+
+fn wrapping_sub(_: &mut Io, args: Vec<Value>) -> Result<Value, Stop> {
+    let (a, b) = ints("wrapping_sub", &args, false)?;
+    Ok(Value::Int(a.wrapping_sub(b)))
+}
+
+fn wrapping_mul(_: &mut Io, args: Vec<Value>) -> Result<Value, Stop> {
+    let (a, b) = ints("wrapping_mul", &args, false)?;
+    Ok(Value::Int(a.wrapping_mul(b)))
+}
+
+fn wrapping_div(_: &mut Io, args: Vec<Value>) -> Result<Value, Stop> {
+    let (a, b) = ints("wrapping_div", &args, true)?;
+    Ok(Value::Int(a.wrapping_div(b)))
+}
+
+fn wrapping_rem(_: &mut Io, args: Vec<Value>) -> Result<Value, Stop> {
+    let (a, b) = ints("wrapping_rem", &args, true)?;
+    Ok(Value::Int(a.wrapping_rem(b)))
+}
+
+// arithmetic that stops at the smallest or largest int instead of overflowing
+fn saturating_add(_: &mut Io, args: Vec<Value>) -> Result<Value, Stop> {
+    let (a, b) = ints("saturating_add", &args, false)?;
+    Ok(Value::Int(a.saturating_add(b)))
+}
+
+fn saturating_sub(_: &mut Io, args: Vec<Value>) -> Result<Value, Stop> {
+    let (a, b) = ints("saturating_sub", &args, false)?;
+    Ok(Value::Int(a.saturating_sub(b)))
+}
+
+fn saturating_mul(_: &mut Io, args: Vec<Value>) -> Result<Value, Stop> {
+    let (a, b) = ints("saturating_mul", &args, false)?;
+    Ok(Value::Int(a.saturating_mul(b)))
+}
+
+fn saturating_div(_: &mut Io, args: Vec<Value>) -> Result<Value, Stop> {
+    let (a, b) = ints("saturating_div", &args, true)?;
+    Ok(Value::Int(a.saturating_div(b)))
+}
+
+/// stops the script, with exit code 0 unless one is given
+fn exit(io: &mut Io, args: Vec<Value>) -> Result<Value, Stop> {
+    io.output.flush().map_err(write_error)?;
+    match args.first() {
+        None => Err(Stop::Exit(0)),
+        Some(Value::Int(code)) => Err(Stop::Exit(i32::try_from(*code).map_err(|_| "exit code out of range")?)),
+        Some(other) => Err(format!("'exit' expects an int, found {}", other.type_name()).into()),
+    }
+}
@@ -0,0 +1,75 @@
+//! checks over a parsed file for mistakes that do not stop it from running, which are reported
+//! as warnings rather than errors
+
+use std::collections::HashMap;
+
+use crate::diagnostic::Diagnostic;
+use crate::lexer::TokenType;
+use crate::parser::{Tree, TreeType};
+use crate::symbol::Symbol;
+
+/// warnings about a File tree, in the order of the trees they are about
+pub fn check(file: &Tree) -> Vec<Diagnostic> {
+    let mut enums = HashMap::new();
+    for item in &file.params {
+        let item = match item.value {
+            TreeType::Pub => &item.params[0],
+            _ => item,
+        };
+        if item.value == TreeType::Enum {
+            enums.insert(name(&item.params[0]), item);
+        }
+    }
+    let mut warnings = Vec::new();
+    visit(file, &enums, &mut warnings);
+    warnings
+}
+
+fn visit(tree: &Tree, enums: &HashMap<Symbol, &Tree>, warnings: &mut Vec<Diagnostic>) {
+    if tree.value == TreeType::Switch
+        && let Some(missing) = unhandled_variants(tree, enums)
+        && !missing.is_empty() {
+        warnings.push(Diagnostic::warning(Some(tree.line), format!("switch does not handle {}", missing.join(", "))));
+    }
+    for param in &tree.params {
+        visit(param, enums, warnings);
+    }
+}
+
+/// the variants of the enum a Switch matches against that none of its arms are sure to match,
+/// None if it does not match against a known enum
+pub(crate) fn unhandled_variants(switch: &Tree, enums: &HashMap<Symbol, &Tree>) -> Option<Vec<String>> {
+    // the enum is whichever one the first variant pattern names
+    let enum_name = switch.params[1..].iter()
+        .flat_map(|x| &x.params[0].params)
+        .find(|x| x.value == TreeType::VariantPattern)
+        .map(|x| name(&x.params[0]))?;
+    let declaration = enums.get(&enum_name)?;
+
+    let mut handled = Vec::new();
+    // arms with a guard might not run even if their patterns match
+    for arm in switch.params[1..].iter().filter(|x| x.params.len() < 3) {
+        for pattern in &arm.params[0].params {
+            match pattern.value {
+                TreeType::Leaf(TokenType::Identifier(_)) => return Some(Vec::new()),
+                TreeType::VariantPattern if name(&pattern.params[0]) == enum_name
+                    && pattern.params[2..].iter().all(|x| matches!(x.value, TreeType::Leaf(TokenType::Identifier(_)))) => {
+                    handled.push(name(&pattern.params[1]));
+                },
+                _ => {},
+            }
+        }
+    }
+    Some(declaration.params[1..].iter()
+        .map(|x| name(&x.params[0]))
+        .filter(|x| !handled.contains(x))
+        .map(|x| format!("{enum_name}.{x}"))
+        .collect())
+}
+
+fn name(leaf: &Tree) -> Symbol {
+    match &leaf.value {
+        TreeType::Leaf(TokenType::Identifier(name)) => *name,
+        other => panic!("({}) expected identifier, found {other:?}", leaf.line),
+    }
+}
@@ -0,0 +1,86 @@
+//! concrete syntax tree, the parsed tree with every token and bit of trivia attached so that the
+//! original source can be rebuilt byte for byte
+
+use crate::lexer::{self, Trivia, TriviaToken};
+use crate::parser::{self, Tree, TreeType};
+
+pub struct Cst {
+    pub root: SyntaxNode,
+    pub trailing: Vec<Trivia>, // trivia after the last token
+}
+
+/// a tree from the parser, with its leaves replaced by the tokens they came from and the
+/// punctuation that the parser drops put back in
+pub struct SyntaxNode {
+    pub kind: TreeType,
+    pub children: Vec<SyntaxElement>,
+}
+
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(TriviaToken),
+}
+
+/// lexes and parses input losslessly
+pub fn parse(input: &str) -> Cst {
+    let (tokens, trailing) = lexer::lex_lossless(input);
+    let tree = parser::parse(tokens.iter().map(|x| x.token.clone()).collect());
+    let mut tokens = tokens.into_iter().peekable();
+    let root = build(&tree, &mut tokens);
+    Cst{
+        root,
+        trailing,
+    }
+}
+
+/// pairs up a tree with the tokens inside of its span, recursing into every child that is not a leaf
+fn build(tree: &Tree, tokens: &mut std::iter::Peekable<std::vec::IntoIter<TriviaToken>>) -> SyntaxNode {
+    let mut node = SyntaxNode{
+        kind: tree.value.clone(),
+        children: Vec::new(),
+    };
+    // leaves are stood in for by their token, so only the other children need nodes
+    let mut params = tree.params.iter().filter(|x| !matches!(x.value, TreeType::Leaf(_))).peekable();
+    loop {
+        let next_start = tokens.peek().map(|x| x.token.start).filter(|x| *x < tree.end);
+        match (params.peek(), next_start) {
+            (Some(child), Some(start)) if start < child.start => {
+                node.children.push(SyntaxElement::Token(tokens.next().unwrap()));
+            },
+            (Some(child), _) => {
+                node.children.push(SyntaxElement::Node(build(child, tokens)));
+                params.next();
+            },
+            (None, Some(_)) => node.children.push(SyntaxElement::Token(tokens.next().unwrap())),
+            (None, None) => break,
+        }
+    }
+    node
+}
+
+impl std::fmt::Display for Cst {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.root)?;
+        for trivia in &self.trailing {
+            write!(f, "{}", trivia.text())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => write!(f, "{node}")?,
+                SyntaxElement::Token(token) => {
+                    for trivia in &token.leading {
+                        write!(f, "{}", trivia.text())?;
+                    }
+                    write!(f, "{}", token.text)?;
+                },
+            }
+        }
+        Ok(())
+    }
+}
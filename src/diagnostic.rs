@@ -0,0 +1,156 @@
+//! the lexer and parser report errors by panicking with a message of the form "(line) message",
+//! this turns those panics back into values for tools that need to keep running afterwards
+
+use std::cell::Cell;
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub file: Option<String>, // None for the file being run or checked, otherwise the module it came from
+    pub line: Option<usize>, // None if the panic did not say which line it happened on
+    pub severity: Severity,
+    pub message: String,
+    pub trace: Vec<Frame>, // the calls a runtime error unwound through, innermost first
+}
+
+/// a call that a runtime error happened inside of
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub function: String, // how the function is referred to in errors, e.g. "'main'" or "lambda"
+    pub args: Vec<String>, // each parameter and its value when the error happened, e.g. "x: 1"
+    pub file: Option<String>, // the module it was called from, None for the file being run
+    pub line: Option<usize>, // where it was called from, None if it was called by whatever is running the script
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning, // something that is probably a mistake but does not stop the file from running
+}
+
+impl Diagnostic {
+    /// an error without a file
+    pub fn new(line: Option<usize>, message: impl Into<String>) -> Self {
+        Diagnostic{
+            file: None,
+            line,
+            severity: Severity::Error,
+            message: message.into(),
+            trace: Vec::new(),
+        }
+    }
+
+    /// a warning without a file
+    pub fn warning(line: Option<usize>, message: impl Into<String>) -> Self {
+        Diagnostic{
+            severity: Severity::Warning,
+            ..Diagnostic::new(line, message)
+        }
+    }
+
+    /// the diagnostic as it is shown to whoever is running a script, with the line of source it is
+    /// about and, for a runtime error, each call it happened inside of along with the line it was
+    /// called from
+    /// path and source are of the file being run, the source of modules is read from their files
+    pub fn render(&self, path: &str, source: &str) -> String {
+        let mut output = match self.severity {
+            Severity::Error => format!("error: {}\n", self.message),
+            Severity::Warning => format!("warning: {}\n", self.message),
+        };
+        // every snippet is given the same gutter, wide enough for the longest line number
+        let lines = std::iter::once(self.line).chain(self.trace.iter().map(|x| x.line));
+        let width = lines.flatten().max().unwrap_or(0).to_string().len();
+        snippet(&mut output, self.file.as_deref(), self.line, path, source, width);
+        for frame in &self.trace {
+            output.push_str(&format!("note: in {}({})\n", frame.function, frame.args.join(", ")));
+            snippet(&mut output, frame.file.as_deref(), frame.line, path, source, width);
+        }
+        output
+    }
+
+    fn from_panic(payload: Box<dyn std::any::Any + Send>) -> Self {
+        let text = match payload.downcast::<String>() {
+            Ok(text) => *text,
+            Err(payload) => match payload.downcast::<&str>() {
+                Ok(text) => text.to_string(),
+                Err(_) => String::from("unknown error"),
+            },
+        };
+        // split "(line) message" into its parts
+        if let Some(rest) = text.strip_prefix('(')
+            && let Some((line, message)) = rest.split_once(") ")
+            && let Ok(line) = line.parse() {
+            return Diagnostic::new(Some(line), message);
+        }
+        Diagnostic::new(None, text)
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{file}: ")?;
+        }
+        match self.line {
+            Some(line) => write!(f, "({line}) {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::fmt::Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "in {}({})", self.function, self.args.join(", "))?;
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, ", called at {file}:{line}"),
+            (None, Some(line)) => write!(f, ", called at line {line}"),
+            (_, None) => Ok(()),
+        }
+    }
+}
+
+/// writes where line of file is, followed by the line itself if it can be found, where a file of
+/// None is the one at path with source
+fn snippet(output: &mut String, file: Option<&str>, line: Option<usize>, path: &str, source: &str, width: usize) {
+    let Some(line) = line else {
+        if let Some(file) = file {
+            output.push_str(&format!("  --> {file}\n"));
+        }
+        return;
+    };
+    let module_source;
+    let (file, source) = match file {
+        Some(file) => {
+            module_source = std::fs::read_to_string(file).ok();
+            (file, module_source.as_deref())
+        },
+        None => (path, Some(source)),
+    };
+    output.push_str(&format!("  --> {file}:{line}\n"));
+    let Some(text) = source.and_then(|x| x.lines().nth(line.wrapping_sub(1))) else {
+        return;
+    };
+    let gutter = " ".repeat(width);
+    output.push_str(&format!("{gutter} |\n{line:>width$} | {text}\n{gutter} |\n"));
+}
+
+thread_local! {
+    // whether the current thread is inside of catch, in which case panics are not printed
+    static CATCHING: Cell<bool> = const { Cell::new(false) };
+}
+static INSTALL_HOOK: std::sync::Once = std::sync::Once::new();
+
+/// runs f, returning the error it panicked with instead of unwinding any further
+pub fn catch<T>(f: impl FnOnce() -> T) -> Result<T, Diagnostic> {
+    INSTALL_HOOK.call_once(|| {
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if !CATCHING.get() {
+                default_hook(info);
+            }
+        }));
+    });
+    let was_catching = CATCHING.replace(true);
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+    CATCHING.set(was_catching);
+    result.map_err(Diagnostic::from_panic)
+}
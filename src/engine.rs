@@ -0,0 +1,129 @@
+//! the interface for running scripts from inside another rust program
+//!
+//! an Engine keeps its functions and globals between calls to run, so a host can load a script
+//! once and then call into it as often as it likes
+//!
+//! scripts run on the host's own thread, so deep recursion in a script needs a thread with a large
+//! stack to get as far as the "stack overflow" error, several megabytes in a debug build
+
+use std::io::Write;
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::builtins::{Io, Stop};
+use crate::diagnostic::{self, Diagnostic};
+use crate::interpreter::{Exit, Interpreter};
+use crate::lexer;
+use crate::optimize;
+use crate::parser;
+use crate::types;
+use crate::value::Value;
+
+pub struct Engine {
+    interpreter: Interpreter,
+}
+
+/// why a script could not be run or did not finish
+#[derive(Debug, Clone)]
+pub enum Error {
+    Syntax(Diagnostic), // the source could not be lexed or parsed, nothing was run
+    Type(Diagnostic), // the first type error in the source, or overflow in a constant expression, nothing was run
+    Runtime(Diagnostic),
+    Exit(i32), // the script called exit()
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Syntax(error) => write!(f, "syntax error: {error}"),
+            Error::Type(error) => write!(f, "type error: {error}"),
+            Error::Runtime(error) => write!(f, "runtime error: {error}"),
+            Error::Exit(code) => write!(f, "script exited with code {code}"),
+        }
+    }
+}
+impl std::error::Error for Error {}
+
+impl From<Exit> for Error {
+    fn from(exit: Exit) -> Self {
+        match exit {
+            Exit::Code(code) => Error::Exit(code),
+            Exit::Error(error) => Error::Runtime(error),
+        }
+    }
+}
+
+impl Engine {
+    /// an engine whose scripts read from stdin and print to stdout
+    pub fn new() -> Self {
+        Self::with_io(Io::stdio())
+    }
+
+    pub fn with_io(io: Io) -> Self {
+        Engine{
+            interpreter: Interpreter::new(io),
+        }
+    }
+
+    /// lexes and parses source, declares its functions and runs its top level statements
+    /// unlike running a file from the command line, main is not called
+    /// imports are resolved relative to the working directory
+    pub fn run(&mut self, source: &str) -> Result<(), Error> {
+        self.load(source, None)
+    }
+
+    /// reads and runs the script at path like run, resolving its imports relative to it
+    pub fn run_file(&mut self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|_| {
+            Error::Syntax(Diagnostic::new(None, format!("cannot read '{}'", path.display())))
+        })?;
+        self.load(&source, Some(path))
+    }
+
+    fn load(&mut self, source: &str, path: Option<&Path>) -> Result<(), Error> {
+        let mut tree = diagnostic::catch(|| parser::parse(lexer::lex(source))).map_err(Error::Syntax)?;
+        if let Some(error) = types::check(&tree).into_iter().next() {
+            return Err(Error::Type(error));
+        }
+        if let Some(error) = optimize::optimize(&mut tree).into_iter().next() {
+            return Err(Error::Type(error));
+        }
+        let result = self.interpreter.load(&tree, path);
+        let _ = self.interpreter.io.output.flush();
+        Ok(result?)
+    }
+
+    /// calls a function declared by a script that has been run, or a registered or builtin function
+    pub fn call(&mut self, name: &str, args: Vec<Value>) -> Result<Value, Error> {
+        let result = self.interpreter.call(name, args);
+        let _ = self.interpreter.io.output.flush();
+        Ok(result?)
+    }
+
+    /// makes a rust function callable from scripts, an Err it returns becomes a runtime error at
+    /// the line of the call
+    pub fn register_fn(&mut self, name: &str, function: impl Fn(&[Value]) -> Result<Value, String> + 'static) {
+        self.interpreter.register(name, Rc::new(move |args: Vec<Value>| function(&args).map_err(Stop::Error)));
+    }
+
+    /// the value of a global variable, None if no script has declared or assigned it
+    pub fn global(&self, name: &str) -> Option<&Value> {
+        self.interpreter.global(name)
+    }
+
+    /// assigns to a global variable, declaring it if no script has yet
+    pub fn set_global(&mut self, name: &str, value: impl Into<Value>) -> Result<(), Error> {
+        self.interpreter.set_global(name, value.into()).map_err(Error::Runtime)
+    }
+
+    pub fn has_function(&self, name: &str) -> bool {
+        self.interpreter.has_function(name)
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,456 @@
+use crate::lexer::{self, Comment, TokenType};
+use crate::parser::{self, Tree, TreeType};
+
+const INDENT: &str = "  ";
+
+/// lexes, parses and formats input, returning the canonical version of the source
+pub fn format_source(input: &str) -> String {
+    let (tokens, comments) = lexer::lex_with_comments(input);
+    let tree = parser::parse(tokens);
+    format(&tree, &comments)
+}
+
+/// turns a File tree back into source code, placing comments back near the trees they were
+/// found next to
+pub fn format(tree: &Tree, comments: &[Comment]) -> String {
+    let mut formatter = Formatter{
+        output: String::new(),
+        comments,
+        next_comment: 0,
+        indent: 0,
+        last_line: None,
+    };
+    let mut prev: Option<&Tree> = None;
+    for item in &tree.params {
+        // top level functions are always separated from their neighbours by a blank line
+        let force_blank = prev.is_some_and(is_function) || (prev.is_some() && is_function(item));
+        let force_blank = formatter.leading_comments(item.line, force_blank);
+        formatter.new_line(item.line, force_blank);
+        formatter.statement(item);
+        prev = Some(item);
+    }
+    formatter.leading_comments(usize::MAX, false);
+    formatter.output
+}
+
+/// whether a top level tree is a function declaration, exported or not
+fn is_function(tree: &Tree) -> bool {
+    match tree.value {
+        TreeType::Function => true,
+        TreeType::Pub => tree.params[0].value == TreeType::Function,
+        _ => false,
+    }
+}
+
+struct Formatter<'a> {
+    output: String,
+    comments: &'a [Comment],
+    next_comment: usize, // index of the first comment that has not been written yet
+    indent: usize,
+    last_line: Option<usize>, // source line of the last thing written, None at the start of a block
+}
+
+impl Formatter<'_> {
+    /// starts a new line at the current indent, preceded by a blank line if the source had one
+    /// between the last thing written and line
+    fn new_line(&mut self, line: usize, force_blank: bool) {
+        if self.last_line.is_some_and(|x| force_blank || line > x + 1) {
+            self.output.push('\n');
+        }
+        for _ in 0..self.indent {
+            self.output.push_str(INDENT);
+        }
+        self.last_line = Some(line);
+    }
+
+    /// writes every comment found before line on its own line
+    /// returns whether a forced blank line is still owed to whatever comes next
+    fn leading_comments(&mut self, line: usize, force_blank: bool) -> bool {
+        let mut force_blank = force_blank;
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.line >= line {
+                break;
+            }
+            self.new_line(comment.line, force_blank);
+            self.output.push_str(&format!("//{}\n", comment.text));
+            self.next_comment += 1;
+            force_blank = false;
+        }
+        force_blank
+    }
+
+    /// writes a comment found on line after whatever is already on the current line
+    fn trailing_comment(&mut self, line: usize) {
+        if let Some(comment) = self.comments.get(self.next_comment)
+            && comment.line == line {
+            self.output.push_str(&format!(" //{}", comment.text));
+            self.next_comment += 1;
+        }
+    }
+
+    /// writes a statement, assuming the current line has already been started
+    fn statement(&mut self, tree: &Tree) {
+        match &tree.value {
+            TreeType::Assign => {
+                self.output.push_str(&format!("{} {}", leaf_text(&tree.params[0]), leaf_text(&tree.params[1])));
+                if tree.params[2].value != TreeType::Inferred {
+                    self.output.push_str(&format!(": {}", type_text(&tree.params[2])));
+                }
+                if let Some(exp) = tree.params.get(3) {
+                    let exp = self.expression(exp);
+                    self.output.push_str(&format!(" = {exp}"));
+                }
+                self.output.push(';');
+            },
+            TreeType::Reassign => {
+                let (lhs, rhs) = (self.expression(&tree.params[0]), self.expression(&tree.params[1]));
+                self.output.push_str(&format!("{lhs} = {rhs};"));
+            },
+            TreeType::Expression => {
+                let exp = self.expression(tree);
+                self.output.push_str(&format!("{exp};"));
+            },
+            TreeType::Return => {
+                match tree.params.first() {
+                    Some(exp) => {
+                        let exp = self.expression(exp);
+                        self.output.push_str(&format!("return {exp};"));
+                    },
+                    None => self.output.push_str("return;"),
+                }
+            },
+            TreeType::Throw => {
+                let exp = self.expression(&tree.params[0]);
+                self.output.push_str(&format!("throw {exp};"));
+            },
+            TreeType::Function => {
+                self.output.push_str(&format!("fn {}", leaf_text(&tree.params[0])));
+                if let Some(type_params) = tree.params.get(4) {
+                    self.output.push_str(&type_parameters(type_params));
+                }
+                self.signature_and_body(&tree.params[1..4]);
+            },
+            TreeType::While => {
+                self.output.push_str(&format!("while ({}) ", self.expression(&tree.params[0])));
+                self.body(&tree.params[1]);
+            },
+            TreeType::For => {
+                let iterable = &tree.params[1];
+                let iterable = match &iterable.value {
+                    TreeType::Range(op) => format!("{}{op}{}", self.expression(&iterable.params[0]), self.expression(&iterable.params[1])),
+                    _ => self.expression(iterable),
+                };
+                self.output.push_str(&format!("for {} in {iterable} ", leaf_text(&tree.params[0])));
+                self.body(&tree.params[2]);
+            },
+            TreeType::Enum => {
+                self.output.push_str(&format!("enum {} ", leaf_text(&tree.params[0])));
+                self.block(tree, &tree.params[1..], |formatter, variant| {
+                    formatter.output.push_str(&leaf_text(&variant.params[0]));
+                    if variant.params.len() > 1 {
+                        let fields: Vec<String> = variant.params[1..].iter().map(type_text).collect();
+                        formatter.output.push_str(&format!("({})", fields.join(", ")));
+                    }
+                    formatter.output.push(',');
+                    formatter.trailing_comment(variant.end_line);
+                });
+            },
+            TreeType::Struct => {
+                self.output.push_str(&format!("struct {}{} ", leaf_text(&tree.params[0]), type_parameters(&tree.params[1])));
+                self.block(tree, &tree.params[2..], |formatter, field| {
+                    formatter.output.push_str(&format!("{}: {},", leaf_text(&field.params[0]), type_text(&field.params[1])));
+                    formatter.trailing_comment(field.end_line);
+                });
+            },
+            TreeType::Switch => {
+                self.output.push_str(&format!("switch ({}) ", self.expression(&tree.params[0])));
+                self.block(tree, &tree.params[1..], |formatter, arm| {
+                    let patterns: Vec<String> = arm.params[0].params.iter().map(pattern).collect();
+                    formatter.output.push_str(&patterns.join(", "));
+                    if let Some(guard) = arm.params.get(2) {
+                        formatter.output.push_str(&format!(" if ({})", formatter.expression(guard)));
+                    }
+                    formatter.output.push(' ');
+                    formatter.body(&arm.params[1]);
+                    formatter.trailing_comment(arm.end_line);
+                });
+            },
+            TreeType::Forever => {
+                self.output.push_str("forever ");
+                self.body(&tree.params[0]);
+            },
+            TreeType::Try => {
+                self.output.push_str("try ");
+                self.body(&tree.params[0]);
+                self.output.push_str(&format!(" catch {} ", leaf_text(&tree.params[1])));
+                self.body(&tree.params[2]);
+            },
+            TreeType::If => {
+                match tree.params[0].value {
+                    TreeType::Let => self.output.push_str(&format!("if let {} = {} ",
+                        leaf_text(&tree.params[0].params[0]), self.expression(&tree.params[0].params[1]))),
+                    _ => self.output.push_str(&format!("if ({}) ", self.expression(&tree.params[0]))),
+                }
+                self.body(&tree.params[1]);
+                if let Some(other) = tree.params.get(2) {
+                    self.output.push_str(" else ");
+                    match other.value {
+                        TreeType::If => {
+                            self.statement(other);
+                            return; // the trailing comment has already been written by the inner if
+                        },
+                        _ => self.body(other),
+                    }
+                }
+            },
+            TreeType::Label => {
+                self.output.push_str(&format!("{}: ", leaf_text(&tree.params[0])));
+                self.statement(&tree.params[1]);
+                return; // the loop has already finished the line
+            },
+            TreeType::Break | TreeType::Continue => {
+                self.output.push_str(if tree.value == TreeType::Break { "break" } else { "continue" });
+                if let Some(label) = tree.params.first() {
+                    self.output.push_str(&format!(" {}", leaf_text(label)));
+                }
+                self.output.push(';');
+            },
+            TreeType::Defer => {
+                self.output.push_str("defer ");
+                self.statement(&tree.params[0]);
+                return; // the deferred statement has already finished the line
+            },
+            TreeType::Import => {
+                self.output.push_str(&format!("import {};", leaf_text(&tree.params[0])));
+            },
+            TreeType::Pub => {
+                self.output.push_str("pub ");
+                self.statement(&tree.params[0]);
+                return; // the item has already finished the line
+            },
+            other => panic!("({}) cannot format {other:?} as a statement", tree.line),
+        }
+        self.trailing_comment(tree.end_line);
+        self.output.push('\n');
+        self.last_line = Some(tree.end_line);
+    }
+
+    /// writes '{', each of items on its own line using f and then '}', without a trailing newline
+    /// for the parts of trees like enums that are not statements but are laid out like them
+    fn block(&mut self, tree: &Tree, items: &[Tree], f: impl Fn(&mut Self, &Tree)) {
+        let has_comments = self.comments.get(self.next_comment).is_some_and(|x| x.line < tree.end_line);
+        if items.is_empty() && !has_comments {
+            self.output.push_str("{}");
+            return;
+        }
+        self.output.push_str("{\n");
+        self.indent += 1;
+        self.last_line = None;
+        for item in items {
+            self.leading_comments(item.line, false);
+            self.new_line(item.line, false);
+            f(self, item);
+            self.output.push('\n');
+            self.last_line = Some(item.end_line);
+        }
+        self.leading_comments(tree.end_line, false);
+        self.indent -= 1;
+        for _ in 0..self.indent {
+            self.output.push_str(INDENT);
+        }
+        self.output.push('}');
+    }
+
+    /// writes the parameters, return type and body of a function or lambda
+    fn signature_and_body(&mut self, params: &[Tree]) {
+        let [fn_params, ret_type, body] = params else {
+            panic!("expected parameters, return type and body");
+        };
+        let fn_params: Vec<String> = fn_params.params.iter()
+            .map(|x| format!("{}: {}", leaf_text(&x.params[0]), type_text(&x.params[1])))
+            .collect();
+        self.output.push_str(&format!("({})", fn_params.join(", ")));
+        if !matches!(ret_type.value, TreeType::Leaf(TokenType::Void)) {
+            self.output.push_str(&format!(" -> {}", type_text(ret_type)));
+        }
+        self.output.push(' ');
+        self.body(body);
+    }
+
+    /// formats an expression that starts on the current line, indenting the lines of any lambda
+    /// bodies in it to match
+    fn expression(&self, tree: &Tree) -> String {
+        let indent = INDENT.repeat(self.indent);
+        expression(tree).replace('\n', &format!("\n{indent}"))
+    }
+
+    /// writes '{', the statements of a body and then '}', without a trailing newline
+    fn body(&mut self, tree: &Tree) {
+        let has_comments = self.comments.get(self.next_comment).is_some_and(|x| x.line < tree.end_line);
+        if tree.params.is_empty() && !has_comments {
+            self.output.push_str("{}");
+            return;
+        }
+        self.output.push('{');
+        // a comment on the line of the '{' stays there, unless the body shares that line with
+        // its contents, in which case the comment belongs to them instead
+        if tree.end_line > tree.line && tree.params.first().is_none_or(|x| x.line > tree.line) {
+            self.trailing_comment(tree.line);
+        }
+        self.output.push('\n');
+        self.indent += 1;
+        self.last_line = None;
+        for statement in &tree.params {
+            self.leading_comments(statement.line, false);
+            self.new_line(statement.line, false);
+            self.statement(statement);
+        }
+        // comments after the last statement but before the '}'
+        self.leading_comments(tree.end_line, false);
+        self.indent -= 1;
+        for _ in 0..self.indent {
+            self.output.push_str(INDENT);
+        }
+        self.output.push('}');
+    }
+}
+
+/// formats an expression tree on a single line, adding parentheses only where precedence needs them
+pub fn expression(tree: &Tree) -> String {
+    match &tree.value {
+        TreeType::Expression => expression(&tree.params[0]),
+        TreeType::Leaf(_) => leaf_text(tree),
+        TreeType::BinaryOp(op) => {
+            let prec = parser::precedence(op).unwrap();
+            let lhs = &tree.params[0];
+            let rhs = &tree.params[1];
+            // operators are left associative, so a right operand of equal precedence needs parentheses
+            let lhs_text = if tree_precedence(lhs) < prec { format!("({})", expression(lhs)) }
+                else { expression(lhs) };
+            let rhs_text = if tree_precedence(rhs) <= prec { format!("({})", expression(rhs)) }
+                else { expression(rhs) };
+            format!("{lhs_text} {op} {rhs_text}")
+        },
+        TreeType::UnaryOp(op) => {
+            let operand = &tree.params[0];
+            match operand.value {
+                TreeType::BinaryOp(_) => format!("{op}({})", expression(operand)),
+                _ => format!("{op}{}", expression(operand)),
+            }
+        },
+        TreeType::Call => {
+            let args: Vec<String> = tree.params[1].params.iter().map(expression).collect();
+            format!("{}({})", postfix_operand(&tree.params[0]), args.join(", "))
+        },
+        TreeType::Member => format!("{}.{}", postfix_operand(&tree.params[0]), leaf_text(&tree.params[1])),
+        TreeType::Array => {
+            let elements: Vec<String> = tree.params.iter().map(expression).collect();
+            format!("[{}]", elements.join(", "))
+        },
+        // laid out like a function at the top level, the caller indents it to where it ends up
+        TreeType::Lambda => {
+            let mut formatter = Formatter{
+                output: String::from("\\"),
+                comments: &[],
+                next_comment: 0,
+                indent: 0,
+                last_line: None,
+            };
+            formatter.signature_and_body(&tree.params);
+            formatter.output
+        },
+        other => panic!("({}) cannot format {other:?} as an expression", tree.line),
+    }
+}
+
+/// formats a pattern of a switch arm
+fn pattern(tree: &Tree) -> String {
+    match &tree.value {
+        TreeType::Leaf(_) => leaf_text(tree),
+        TreeType::Range(op) => format!("{}{op}{}", leaf_text(&tree.params[0]), leaf_text(&tree.params[1])),
+        TreeType::VariantPattern => {
+            let mut text = format!("{}.{}", leaf_text(&tree.params[0]), leaf_text(&tree.params[1]));
+            if tree.params.len() > 2 {
+                let fields: Vec<String> = tree.params[2..].iter().map(pattern).collect();
+                text.push_str(&format!("({})", fields.join(", ")));
+            }
+            text
+        },
+        other => panic!("({}) cannot format {other:?} as a pattern", tree.line),
+    }
+}
+
+/// formats what a call or member access applies to, which needs parentheses if it is an operator
+fn postfix_operand(tree: &Tree) -> String {
+    match tree.value {
+        TreeType::BinaryOp(_) | TreeType::UnaryOp(_) => format!("({})", expression(tree)),
+        _ => expression(tree),
+    }
+}
+
+/// precedence of the root of an expression tree, anything that is not a binary operator binds
+/// tighter than every binary operator
+fn tree_precedence(tree: &Tree) -> u8 {
+    match &tree.value {
+        TreeType::BinaryOp(op) => parser::precedence(op).unwrap(),
+        _ => u8::MAX,
+    }
+}
+
+/// source text of a type, a leaf, FunctionType, GenericType or OptionalType
+fn type_text(tree: &Tree) -> String {
+    match &tree.value {
+        TreeType::OptionalType => format!("{}?", type_text(&tree.params[0])),
+        TreeType::FunctionType => {
+            let (ret_type, params) = tree.params.split_last().unwrap();
+            let params: Vec<String> = params.iter().map(type_text).collect();
+            match ret_type.value {
+                TreeType::Leaf(TokenType::Void) => format!("fn({})", params.join(", ")),
+                _ => format!("fn({}) -> {}", params.join(", "), type_text(ret_type)),
+            }
+        },
+        TreeType::GenericType => {
+            let args: Vec<String> = tree.params[1..].iter().map(type_text).collect();
+            format!("{}<{}>", leaf_text(&tree.params[0]), args.join(", "))
+        },
+        _ => leaf_text(tree),
+    }
+}
+
+/// source text of a TypeParameters tree, which is nothing if there are none
+fn type_parameters(tree: &Tree) -> String {
+    if tree.params.is_empty() {
+        return String::new();
+    }
+    let names: Vec<String> = tree.params.iter().map(leaf_text).collect();
+    format!("<{}>", names.join(", "))
+}
+
+/// source text of a leaf, literals are written so that they lex back to the same token
+fn leaf_text(tree: &Tree) -> String {
+    match &tree.value {
+        TreeType::Leaf(TokenType::FloatLit(flt)) => {
+            let mut text = flt.to_string();
+            if !text.contains('.') {
+                text.push_str(".0");
+            }
+            text
+        },
+        TreeType::Leaf(TokenType::StringLit(string)) => {
+            let mut text = String::from("\"");
+            for c in string.chars() {
+                match c {
+                    '\t' => text.push_str("\\t"),
+                    '\n' => text.push_str("\\n"),
+                    '\\' => text.push_str("\\\\"),
+                    '"' => text.push_str("\\\""),
+                    c => text.push(c),
+                }
+            }
+            text.push('"');
+            text
+        },
+        TreeType::Leaf(token) => token.to_string(),
+        other => panic!("({}) expected leaf, found {other:?}", tree.line),
+    }
+}
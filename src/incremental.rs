@@ -0,0 +1,157 @@
+//! keeps the tokens and tree of a file up to date as it is edited, relexing and reparsing only
+//! the parts that an edit can have changed
+//!
+//! both the lexer and the parser only ever look forward, so lexing from the start of a token (or
+//! parsing from the start of a top level tree) gives the same result no matter what came before it.
+//! once the new tokens line up with an old token after the edit, every token after that is the same
+//! as before, only moved, and the same goes for top level trees
+
+use crate::lexer::{self, Token};
+use crate::parser::{self, Tree, TreeType};
+
+/// replaces the bytes in range with text
+#[derive(Debug, Clone)]
+pub struct TextEdit {
+    pub range: std::ops::Range<usize>,
+    pub text: String,
+}
+
+/// a file along with its tokens and tree, always equal to what lexing and parsing text from scratch gives
+pub struct Parsed {
+    pub text: String,
+    pub tokens: Vec<Token>,
+    pub tree: Tree,
+}
+
+impl Parsed {
+    pub fn new(text: String) -> Self {
+        let tokens = lexer::lex(&text);
+        let tree = parser::parse(tokens.clone());
+        Parsed{
+            text,
+            tokens,
+            tree,
+        }
+    }
+
+    /// applies edit to the text, then updates the tokens and tree to match
+    /// panics the same way lexing or parsing the new text would, in which case nothing is changed
+    pub fn edit(&mut self, edit: &TextEdit) {
+        let old_start = edit.range.start;
+        let old_end = edit.range.end;
+        let new_end = old_start + edit.text.len();
+        let mut text = String::with_capacity(self.text.len() - (old_end - old_start) + edit.text.len());
+        text.push_str(&self.text[..old_start]);
+        text.push_str(&edit.text);
+        text.push_str(&self.text[old_end..]);
+        let offset_delta = new_end as isize - old_end as isize;
+        let line_delta = edit.text.matches('\n').count() as isize
+            - self.text[old_start..old_end].matches('\n').count() as isize;
+
+        // tokens ending right at the edit could be extended by it (e.g. '=' becoming '=='), and the
+        // lexer looks up to two chars past the end of a token (an int is only an int if it is not
+        // followed by a '.' and then something other than another '.'), so only tokens ending
+        // before both of those are kept
+        let kept = self.tokens.partition_point(|x| x.end + 1 < old_start);
+        let (relex_start, relex_line) = match kept {
+            0 => (0, 1),
+            kept => {
+                let last = &self.tokens[kept - 1];
+                (last.end, last.line + self.text[last.start..last.end].matches('\n').count())
+            },
+        };
+
+        // relex until a new token starts where a (moved) old token after the edit did
+        let mut old_next = self.tokens.partition_point(|x| x.start < old_end);
+        let mut relexed: Vec<Token> = Vec::new();
+        let mut synced = false;
+        for token in lexer::Lexer::starting_at(&text, relex_start, relex_line) {
+            if token.start >= new_end {
+                while old_next < self.tokens.len()
+                    && (self.tokens[old_next].start as isize + offset_delta) < token.start as isize {
+                    old_next += 1;
+                }
+                if old_next < self.tokens.len()
+                    && (self.tokens[old_next].start as isize + offset_delta) == token.start as isize {
+                    synced = true;
+                    break;
+                }
+            }
+            relexed.push(token.into_owned());
+        }
+        let reused_tokens = if synced { self.tokens.len() - old_next } else { 0 };
+        let mut tokens: Vec<Token> = Vec::with_capacity(kept + relexed.len() + reused_tokens);
+        tokens.extend_from_slice(&self.tokens[..kept]);
+        tokens.append(&mut relexed);
+        let first_reused = tokens.len(); // index of the first token that is carried over from after the edit
+        if synced {
+            tokens.extend(self.tokens[old_next..].iter().map(|x| shift_token(x, offset_delta, line_delta)));
+        }
+
+        // top level trees whose tokens, plus the one after them that the parser might have peeked
+        // at, come before the edit are kept as they are
+        let item_ends: Vec<usize> = self.tree.params.iter()
+            .map(|x| self.tokens.partition_point(|t| t.start < x.end))
+            .collect();
+        let kept_items = item_ends.partition_point(|x| *x < kept);
+        let mut items: Vec<Tree> = self.tree.params[..kept_items].to_vec();
+        let reparse_start = match kept_items {
+            0 => 0,
+            kept_items => item_ends[kept_items - 1],
+        };
+
+        // reparse until a top level tree ends where an old one (after the edit) did
+        let mut old_item = kept_items;
+        let mut synced_item = None;
+        parser::parse_items(&tokens, reparse_start, |item, pos| {
+            items.push(item);
+            if synced && pos >= first_reused {
+                let old_pos = pos - first_reused + old_next;
+                while old_item < item_ends.len() && item_ends[old_item] < old_pos {
+                    old_item += 1;
+                }
+                if old_item < item_ends.len() && item_ends[old_item] == old_pos {
+                    synced_item = Some(old_item + 1);
+                    return false;
+                }
+            }
+            true
+        });
+        if let Some(synced_item) = synced_item {
+            for item in &self.tree.params[synced_item..] {
+                let mut item = item.clone();
+                shift_tree(&mut item, offset_delta, line_delta);
+                items.push(item);
+            }
+        }
+
+        self.tree = Tree{
+            line: 1,
+            end_line: tokens.last().map_or(1, |x| x.line),
+            start: 0,
+            end: tokens.last().map_or(0, |x| x.end),
+            value: TreeType::File,
+            params: items,
+        };
+        self.tokens = tokens;
+        self.text = text;
+    }
+}
+
+fn shift_token(token: &Token, offset_delta: isize, line_delta: isize) -> Token {
+    let mut token = token.clone();
+    token.start = token.start.strict_add_signed(offset_delta);
+    token.end = token.end.strict_add_signed(offset_delta);
+    token.line = token.line.strict_add_signed(line_delta);
+    token
+}
+
+fn shift_tree(tree: &mut Tree, offset_delta: isize, line_delta: isize) {
+    tree.start = tree.start.strict_add_signed(offset_delta);
+    tree.end = tree.end.strict_add_signed(offset_delta);
+    tree.line = tree.line.strict_add_signed(line_delta);
+    tree.end_line = tree.end_line.strict_add_signed(line_delta);
+    for param in &mut tree.params {
+        shift_tree(param, offset_delta, line_delta);
+    }
+}
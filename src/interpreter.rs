@@ -0,0 +1,1124 @@
+//! runs a parsed file by walking its tree
+//!
+//! runtime errors are returned as diagnostics rather than panicking like the lexer and parser do,
+//! since a script can stop for reasons other than an error (returning from a function, exit())
+//! and those need to unwind through the same code
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::builtins::{self, Io, Stop};
+use crate::diagnostic::{self, Diagnostic, Frame};
+use crate::lexer::{self, TokenType};
+use crate::optimize;
+use crate::parser::{self, Tree, TreeType};
+use crate::symbol::{Symbol, SymbolTable};
+use crate::types;
+use crate::value::{self, Function, Value};
+
+/// how deep script function calls can nest before it is treated as infinite recursion
+const MAX_CALL_DEPTH: usize = 256;
+
+/// the stack that run gives scripts, so that they reach MAX_CALL_DEPTH before running out of it
+/// even in a debug build, where each call takes tens of kilobytes
+const STACK_SIZE: usize = 64 << 20;
+
+/// the module that is run directly, rather than being imported
+const ROOT: usize = 0;
+
+/// the struct that caught errors are, with the fields message, line and trace
+pub const ERROR: &str = "Error";
+
+/// a function implemented in rust by whatever is embedding the interpreter
+pub type Native = Rc<dyn Fn(Vec<Value>) -> Result<Value, Stop>>;
+
+pub struct Interpreter {
+    pub io: Io,
+    modules: Vec<Module>, // indexed by module id, the root module is first
+    loaded: HashMap<PathBuf, usize>, // ids of modules by their canonical path
+    loading: Vec<usize>, // modules whose top level is running, each imported by the one before it
+    module: usize, // the module of the code that is currently running
+    natives: HashMap<Symbol, Native>,
+    locals: SymbolTable<Local>, // of the function currently running
+    generics: Rc<[Symbol]>, // type parameters of the function currently running, which accept any value
+    call_depth: usize,
+}
+
+/// everything declared at the top level of one file
+struct Module {
+    file: String, // how the module is referred to in errors
+    dir: PathBuf, // imports are resolved relative to this
+    functions: HashMap<Symbol, Rc<Tree>>,
+    globals: HashMap<Symbol, Variable>,
+    imports: HashMap<Symbol, usize>, // modules by the name they were imported as
+    enums: HashMap<Symbol, Rc<Tree>>,
+    structs: HashMap<Symbol, Rc<Tree>>,
+    exports: HashSet<Symbol>, // functions, globals, enums and structs declared pub
+}
+
+pub(crate) struct Variable {
+    value: Option<Value>, // None until it is first assigned
+    constant: bool,
+    type_name: TokenType, // the declared type, checked on every assignment
+}
+
+/// a local variable, shared with any lambdas that captured it
+pub(crate) type Local = Rc<RefCell<Variable>>;
+
+/// why running some code stopped before reaching its end
+enum Unwind {
+    Return(Value),
+    Break(Option<Symbol>), // the label of the loop to break out of, None for the innermost
+    Continue(Option<Symbol>),
+    Exit(i32),
+    Error(Diagnostic),
+}
+
+/// how a script that stopped early ended
+#[derive(Debug, Clone)]
+pub enum Exit {
+    Code(i32), // exit() was called
+    Error(Diagnostic),
+}
+
+type Flow<T> = Result<T, Unwind>;
+
+fn error<T>(line: usize, message: impl Into<String>) -> Flow<T> {
+    Err(Unwind::Error(Diagnostic::new(Some(line), message)))
+}
+
+impl From<Unwind> for Exit {
+    fn from(unwind: Unwind) -> Self {
+        match unwind {
+            Unwind::Exit(code) => Exit::Code(code),
+            Unwind::Error(error) => Exit::Error(error),
+            Unwind::Return(_) => unreachable!("returns are caught where functions are called"),
+            // the parser only allows these inside of a loop
+            Unwind::Break(_) | Unwind::Continue(_) => unreachable!("breaks and continues are caught by their loop"),
+        }
+    }
+}
+
+/// runs a File tree read from path, calling its main function if it has one, and returns the exit code
+pub fn run(file: &Tree, path: &Path, io: Io) -> Result<i32, Diagnostic> {
+    // on a thread of its own, as the stack of whichever thread called this might be too small
+    std::thread::scope(|scope| {
+        std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn_scoped(scope, || run_here(file, path, io))
+            .expect("could not start a thread to run the script")
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+}
+
+fn run_here(file: &Tree, path: &Path, io: Io) -> Result<i32, Diagnostic> {
+    let mut interpreter = Interpreter::new(io);
+    let result = interpreter.load(file, Some(path)).and_then(|_| match interpreter.has_function("main") {
+        true => interpreter.call("main", Vec::new()).map(|_| ()),
+        false => Ok(()),
+    });
+    let _ = interpreter.io.output.flush();
+    match result {
+        Ok(()) | Err(Exit::Code(0)) => Ok(0),
+        Err(Exit::Code(code)) => Ok(code),
+        Err(Exit::Error(error)) => Err(error),
+    }
+}
+
+impl Module {
+    fn new(file: String, dir: PathBuf) -> Self {
+        Module{
+            file,
+            dir,
+            functions: HashMap::new(),
+            globals: HashMap::new(),
+            imports: HashMap::new(),
+            enums: HashMap::new(),
+            structs: HashMap::new(),
+            exports: HashSet::new(),
+        }
+    }
+}
+
+impl Interpreter {
+    pub fn new(io: Io) -> Self {
+        Interpreter{
+            io,
+            modules: vec![Module::new(String::from("<source>"), PathBuf::from("."))],
+            loaded: HashMap::new(),
+            loading: Vec::new(),
+            module: ROOT,
+            natives: HashMap::new(),
+            locals: SymbolTable::new(),
+            generics: Rc::new([]),
+            call_depth: 0,
+        }
+    }
+
+    /// declares every function in a File tree, then runs its other top level trees in order
+    /// imports are resolved relative to the file at path, or to the working directory without one
+    pub fn load(&mut self, file: &Tree, path: Option<&Path>) -> Result<(), Exit> {
+        if let Some(path) = path {
+            let root = &mut self.modules[ROOT];
+            root.file = path.display().to_string();
+            root.dir = path.parent().map_or(PathBuf::from("."), Path::to_path_buf);
+            if let Ok(path) = path.canonicalize() {
+                self.loaded.insert(path, ROOT);
+            }
+        }
+        self.loading.push(ROOT);
+        let result = self.load_module(file);
+        self.loading.pop();
+        Ok(result?)
+    }
+
+    /// loads a File tree into the current module
+    fn load_module(&mut self, file: &Tree) -> Flow<()> {
+        // functions and imports are available to the top level statements no matter where they are
+        for item in &file.params {
+            let (item, exported) = match item.value {
+                TreeType::Pub => (&item.params[0], true),
+                _ => (item, false),
+            };
+            match item.value {
+                TreeType::Function => {
+                    let name = leaf_symbol(&item.params[0]);
+                    let module = &mut self.modules[self.module];
+                    if module.functions.insert(name, Rc::new(item.clone())).is_some() {
+                        return error(item.line, format!("function '{name}' is already declared"));
+                    }
+                    if exported {
+                        module.exports.insert(name);
+                    }
+                },
+                TreeType::Enum => {
+                    let name = leaf_symbol(&item.params[0]);
+                    let module = &mut self.modules[self.module];
+                    if module.enums.insert(name, Rc::new(item.clone())).is_some() {
+                        return error(item.line, format!("enum '{name}' is already declared"));
+                    }
+                    if exported {
+                        module.exports.insert(name);
+                    }
+                },
+                TreeType::Struct => {
+                    let name = leaf_symbol(&item.params[0]);
+                    let module = &mut self.modules[self.module];
+                    if module.structs.insert(name, Rc::new(item.clone())).is_some() {
+                        return error(item.line, format!("struct '{name}' is already declared"));
+                    }
+                    if exported {
+                        module.exports.insert(name);
+                    }
+                },
+                TreeType::Import => self.import(item)?,
+                _ => {},
+            }
+        }
+        for item in &file.params {
+            let (item, exported) = match item.value {
+                TreeType::Pub => (&item.params[0], true),
+                _ => (item, false),
+            };
+            match item.value {
+                TreeType::Function | TreeType::Enum | TreeType::Struct | TreeType::Import => {},
+                // top level declarations are globals rather than locals
+                TreeType::Assign => {
+                    self.assign(item, true)?;
+                    if exported {
+                        self.modules[self.module].exports.insert(leaf_symbol(&item.params[1]));
+                    }
+                },
+                _ => match self.statement(item) {
+                    Err(Unwind::Return(_)) => return error(item.line, "return outside of a function"),
+                    result => result?,
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// makes the module named by an Import tree available in the current module, loading it first
+    /// if nothing else has yet
+    fn import(&mut self, tree: &Tree) -> Flow<()> {
+        let (name, relative) = match leaf_token(&tree.params[0]) {
+            TokenType::Identifier(name) => (*name, PathBuf::from(format!("{name}.in"))),
+            TokenType::StringLit(path) => {
+                let relative = PathBuf::from(path);
+                match relative.file_stem().and_then(|x| x.to_str()) {
+                    Some(stem) => (Symbol::intern(stem), relative),
+                    None => return error(tree.line, format!("cannot import \"{path}\", it is not a file")),
+                }
+            },
+            other => panic!("({}) expected module name or path, found '{other}'", tree.line),
+        };
+        let path = self.modules[self.module].dir.join(relative);
+        let canonical = match path.canonicalize() {
+            Ok(canonical) => canonical,
+            Err(_) => return error(tree.line, format!("cannot find module '{}'", path.display())),
+        };
+
+        let module = match self.loaded.get(&canonical) {
+            Some(&module) => {
+                // a module that has not finished loading is somewhere up the chain of imports
+                if let Some(start) = self.loading.iter().position(|x| *x == module) {
+                    let mut chain: Vec<&str> = self.loading[start..].iter().map(|x| self.modules[*x].file.as_str()).collect();
+                    chain.push(&self.modules[module].file);
+                    return error(tree.line, format!("import cycle: {}", chain.join(" -> ")));
+                }
+                module
+            },
+            None => {
+                let file = path.display().to_string();
+                let source = match std::fs::read_to_string(&path) {
+                    Ok(source) => source,
+                    Err(_) => return error(tree.line, format!("cannot read module '{file}'")),
+                };
+                let mut parsed = match diagnostic::catch(|| parser::parse(lexer::lex(&source))) {
+                    Ok(parsed) => parsed,
+                    Err(mut error) => {
+                        error.file = Some(file);
+                        return Err(Unwind::Error(error));
+                    },
+                };
+                // a module is type checked when it is loaded, since whoever ran the importer
+                // could only check that
+                if let Some(mut error) = types::check(&parsed).into_iter().next() {
+                    error.file = Some(file);
+                    return Err(Unwind::Error(error));
+                }
+                if let Some(mut error) = optimize::optimize(&mut parsed).into_iter().next() {
+                    error.file = Some(file);
+                    return Err(Unwind::Error(error));
+                }
+                let module = self.modules.len();
+                let dir = path.parent().map_or(PathBuf::from("."), Path::to_path_buf);
+                self.modules.push(Module::new(file, dir));
+                self.loaded.insert(canonical, module);
+
+                let importer = std::mem::replace(&mut self.module, module);
+                self.loading.push(module);
+                let result = self.load_module(&parsed);
+                self.loading.pop();
+                self.module = importer;
+                self.in_module(module, result)?;
+                module
+            },
+        };
+        if self.modules[self.module].imports.insert(name, module).is_some() {
+            return error(tree.line, format!("'{name}' is already imported"));
+        }
+        Ok(())
+    }
+
+    /// marks an error that happened while running code from module as coming from its file
+    fn in_module<T>(&self, module: usize, result: Flow<T>) -> Flow<T> {
+        match result {
+            Err(Unwind::Error(mut error)) if module != ROOT && error.file.is_none() => {
+                error.file = Some(self.modules[module].file.clone());
+                Err(Unwind::Error(error))
+            },
+            result => result,
+        }
+    }
+
+    pub fn has_function(&self, name: &str) -> bool {
+        Symbol::lookup(name).is_some_and(|x| self.modules[ROOT].functions.contains_key(&x))
+    }
+
+    /// makes a native function callable from scripts, in place of any builtin with the same name
+    /// functions declared by scripts still take precedence over it
+    pub fn register(&mut self, name: &str, function: Native) {
+        self.natives.insert(Symbol::intern(name), function);
+    }
+
+    /// calls the script function (or native or builtin) called name
+    pub fn call(&mut self, name: &str, args: Vec<Value>) -> Result<Value, Exit> {
+        let symbol = match Symbol::lookup(name) {
+            Some(symbol) => symbol,
+            // builtins are found by their names, so a script need not have interned them
+            None if builtins::get(name).is_some() => Symbol::intern(name),
+            None => return Err(Exit::Error(Diagnostic::new(None, format!("unknown function '{name}'")))),
+        };
+        match self.call_named(symbol, args, 0) {
+            // there is no call site for errors about the call itself to point at
+            Err(Unwind::Error(error)) if error.line == Some(0) => Err(Exit::Error(Diagnostic::new(None, error.message))),
+            // nor for the outermost call of the trace
+            Err(Unwind::Error(mut error)) => {
+                if let Some(frame) = error.trace.last_mut() {
+                    frame.line = None;
+                }
+                Err(Exit::Error(error))
+            },
+            result => Ok(result?),
+        }
+    }
+
+    /// the value of a global variable, None if it is not declared or not yet assigned
+    pub fn global(&self, name: &str) -> Option<&Value> {
+        self.modules[ROOT].globals.get(&Symbol::lookup(name)?).and_then(|x| x.value.as_ref())
+    }
+
+    /// assigns to a global variable, declaring it with the type of value if it does not exist yet
+    pub fn set_global(&mut self, name: &str, value: Value) -> Result<(), Diagnostic> {
+        let name = Symbol::intern(name);
+        match self.modules[ROOT].globals.get_mut(&name) {
+            Some(variable) if variable.constant && variable.value.is_some() => {
+                return Err(Diagnostic::new(None, format!("cannot assign to constant '{name}'")));
+            },
+            Some(variable) if !value.has_type(&variable.type_name) => {
+                return Err(Diagnostic::new(None,
+                    format!("expected {} for '{name}', found {}", variable.type_name, value.type_name())));
+            },
+            Some(variable) => variable.value = Some(value),
+            None => {
+                self.modules[ROOT].globals.insert(name, Variable{
+                    type_name: value.type_token(),
+                    value: Some(value),
+                    constant: false,
+                });
+            },
+        }
+        Ok(())
+    }
+
+    /// calls a function of the current module, or failing that a native or builtin one
+    fn call_named(&mut self, name: Symbol, args: Vec<Value>, line: usize) -> Flow<Value> {
+        if let Some(function) = self.modules[self.module].functions.get(&name) {
+            let function = declared(function.clone(), self.module);
+            return self.call_function(&function, args, line);
+        }
+        if let Some(native) = self.natives.get(&name) {
+            return match native.clone()(args) {
+                Ok(value) => Ok(value),
+                Err(Stop::Exit(code)) => Err(Unwind::Exit(code)),
+                Err(Stop::Error(message)) => error(line, message),
+            };
+        }
+        match builtins::get(name.as_str()) {
+            Some(builtin) => match builtin.call(&mut self.io, args) {
+                Ok(value) => Ok(value),
+                Err(Stop::Exit(code)) => Err(Unwind::Exit(code)),
+                Err(Stop::Error(message)) => error(line, message),
+            },
+            None => error(line, format!("unknown function '{name}'")),
+        }
+    }
+
+    /// the id of the module that a Member tree refers to, along with the name it accesses, which
+    /// has to be exported from that module
+    fn member(&self, tree: &Tree) -> Flow<(usize, Symbol)> {
+        let module_name = match &tree.params[0].value {
+            TreeType::Leaf(TokenType::Identifier(name)) => *name,
+            _ => return error(tree.line, "only modules have members"),
+        };
+        let module = match self.modules[self.module].imports.get(&module_name) {
+            Some(module) => *module,
+            None => return error(tree.line, format!("unknown module '{module_name}'")),
+        };
+        let name = leaf_symbol(&tree.params[1]);
+        if !self.modules[module].exports.contains(&name) {
+            return error(tree.line, format!("'{name}' is not a public member of '{module_name}'"));
+        }
+        Ok((module, name))
+    }
+
+    /// the declaration of the enum that tree names, either directly or as a member of a module
+    fn enum_declaration(&self, tree: &Tree) -> Option<Rc<Tree>> {
+        match &tree.value {
+            TreeType::Leaf(TokenType::Identifier(name)) => self.modules[self.module].enums.get(name).cloned(),
+            TreeType::Member => {
+                let module = self.member(tree).ok()?.0;
+                self.modules[module].enums.get(&leaf_symbol(&tree.params[1])).cloned()
+            },
+            _ => None,
+        }
+    }
+
+    /// a value of the variant of declaration called variant
+    fn variant(&self, declaration: &Tree, variant: Symbol, fields: Vec<Value>, line: usize) -> Flow<Value> {
+        let name = leaf_symbol(&declaration.params[0]);
+        let types = match declaration.params[1..].iter().find(|x| leaf_symbol(&x.params[0]) == variant) {
+            Some(found) => &found.params[1..],
+            None => return error(line, format!("'{name}' has no variant '{variant}'")),
+        };
+        if fields.len() != types.len() {
+            return error(line, format!("'{name}.{variant}' has {} fields but {} were given", types.len(), fields.len()));
+        }
+        for (field, type_leaf) in fields.iter().zip(types) {
+            let type_name = type_token(type_leaf);
+            if !accepts(&self.generics, field, type_name) {
+                return error(line, format!("expected {type_name} for field of '{name}.{variant}', found {}", field.type_name()));
+            }
+        }
+        Ok(Value::Enum{ name, variant, fields })
+    }
+
+    /// a value of the struct declared by declaration, with args as its fields in order
+    fn construct(&self, declaration: &Tree, args: Vec<Value>, line: usize) -> Flow<Value> {
+        let name = leaf_symbol(&declaration.params[0]);
+        let declared = &declaration.params[2..];
+        if args.len() != declared.len() {
+            return error(line, format!("'{name}' has {} fields but {} were given", declared.len(), args.len()));
+        }
+        let generics: Vec<Symbol> = declaration.params[1].params.iter().map(leaf_symbol).collect();
+        let mut fields = Vec::with_capacity(args.len());
+        for (field, arg) in declared.iter().zip(args) {
+            let field_name = leaf_symbol(&field.params[0]);
+            let type_name = type_token(&field.params[1]);
+            if !accepts(&generics, &arg, type_name) {
+                return error(line, format!("expected {type_name} for field '{field_name}' of '{name}', found {}", arg.type_name()));
+            }
+            fields.push((field_name, arg));
+        }
+        Ok(Value::Struct{ name, fields })
+    }
+
+    /// the field called name of a struct value
+    fn field(&self, value: Value, name: Symbol, line: usize) -> Flow<Value> {
+        match value {
+            Value::Struct{ fields, .. } if fields.iter().any(|x| x.0 == name) => {
+                Ok(fields.into_iter().find(|x| x.0 == name).unwrap().1)
+            },
+            other => error(line, format!("{} has no field '{name}'", other.type_name())),
+        }
+    }
+
+    /// whether tree names an imported module, rather than a variable that has a member
+    fn is_module(&self, tree: &Tree) -> bool {
+        match &tree.value {
+            TreeType::Leaf(TokenType::Identifier(name)) => {
+                let module = &self.modules[self.module];
+                module.imports.contains_key(name) && self.locals.get(*name).is_none() && !module.globals.contains_key(name)
+            },
+            _ => false,
+        }
+    }
+
+    fn call_function(&mut self, function: &Function, args: Vec<Value>, line: usize) -> Flow<Value> {
+        let tree = &function.tree;
+        let module = function.module;
+        // a Function tree starts with its name, a Lambda tree has none
+        let signature = match tree.value {
+            TreeType::Function => &tree.params[1..4],
+            _ => &tree.params[..3],
+        };
+        let [params, return_type, body] = signature else {
+            unreachable!("functions have parameters, a return type and a body");
+        };
+        let name = match function.name() {
+            Some(name) => format!("'{name}'"),
+            None => String::from("lambda"),
+        };
+        let params = &params.params;
+        if args.len() != params.len() {
+            return error(line, format!("{name} takes {} arguments but {} were given", params.len(), args.len()));
+        }
+        if self.call_depth >= MAX_CALL_DEPTH {
+            return error(line, "stack overflow");
+        }
+
+        // the function cannot see the locals of its caller, only the ones it captured
+        let mut locals = SymbolTable::new();
+        for (captured, local) in function.captures.iter() {
+            locals.insert(*captured, local.clone());
+        }
+        locals.push_scope();
+        let caller_generics = std::mem::replace(&mut self.generics, function.generics.clone());
+        for (param, arg) in params.iter().zip(args) {
+            let type_name = type_token(&param.params[1]);
+            if !accepts(&self.generics, &arg, type_name) {
+                self.generics = caller_generics;
+                return error(line, format!("expected {type_name} for parameter '{}' of {name}, found {}",
+                    leaf_symbol(&param.params[0]), arg.type_name()));
+            }
+            locals.insert(leaf_symbol(&param.params[0]), local(Variable{
+                value: Some(arg),
+                constant: false,
+                type_name: type_name.clone(),
+            }));
+        }
+        let caller_locals = std::mem::replace(&mut self.locals, locals);
+        let caller_module = std::mem::replace(&mut self.module, module);
+        self.call_depth += 1;
+        let result = self.body(body);
+        self.call_depth -= 1;
+        self.module = caller_module;
+        let locals = std::mem::replace(&mut self.locals, caller_locals);
+
+        let return_type = type_token(return_type);
+        let value = match result {
+            Ok(()) => Value::Void,
+            Err(Unwind::Return(value)) => value,
+            Err(Unwind::Error(mut error)) => {
+                self.generics = caller_generics;
+                // the arguments as they were when the error happened
+                let args = params.iter().map(|param| {
+                    let name = leaf_symbol(&param.params[0]);
+                    match locals.get(name).and_then(|x| x.borrow().value.clone()) {
+                        Some(Value::String(value)) => format!("{name}: {value:?}"),
+                        Some(value) => format!("{name}: {value}"),
+                        None => format!("{name}: <unassigned>"),
+                    }
+                }).collect();
+                let file = (caller_module != ROOT).then(|| self.modules[caller_module].file.clone());
+                error.trace.push(Frame{ function: name, args, file, line: Some(line) });
+                return self.in_module(module, Err(Unwind::Error(error)));
+            },
+            Err(unwind) => {
+                self.generics = caller_generics;
+                return self.in_module(module, Err(unwind));
+            },
+        };
+        let accepted = accepts(&self.generics, &value, return_type);
+        self.generics = caller_generics;
+        if !accepted {
+            return self.in_module(module, error(tree.end_line,
+                format!("expected {name} to return {return_type}, found {}", value.type_name())));
+        }
+        Ok(value)
+    }
+
+    /// runs f on the local or global variable called name
+    fn with_variable<T>(&mut self, name: Symbol, line: usize, f: impl FnOnce(&mut Variable) -> Flow<T>) -> Flow<T> {
+        if let Some(local) = self.locals.get(name) {
+            return f(&mut local.borrow_mut());
+        }
+        match self.modules[self.module].globals.get_mut(&name) {
+            Some(variable) => f(variable),
+            None => error(line, format!("unknown variable '{name}'")),
+        }
+    }
+
+    /// runs every statement of a Body in a new scope, then its deferred statements last first
+    /// however the body was left, unless the script is exiting
+    fn body(&mut self, body: &Tree) -> Flow<()> {
+        self.locals.push_scope();
+        let mut deferred = Vec::new();
+        let mut result = Ok(());
+        for statement in &body.params {
+            if statement.value == TreeType::Defer {
+                deferred.push(&statement.params[0]);
+                continue;
+            }
+            result = self.statement(statement);
+            if result.is_err() {
+                break;
+            }
+        }
+        if !matches!(result, Err(Unwind::Exit(_))) {
+            for statement in deferred.into_iter().rev() {
+                // an error from a deferred statement only replaces a normal way of leaving the body
+                match self.statement(statement) {
+                    Err(unwind) if !matches!(result, Err(Unwind::Error(_) | Unwind::Exit(_))) => result = Err(unwind),
+                    _ => {},
+                }
+            }
+        }
+        self.locals.pop_scope();
+        result
+    }
+
+    fn statement(&mut self, tree: &Tree) -> Flow<()> {
+        match tree.value {
+            TreeType::Assign => self.assign(tree, false),
+            TreeType::Reassign => {
+                let name = leaf_symbol(&tree.params[0]);
+                let value = self.expression(&tree.params[1])?;
+                let generics = self.generics.clone();
+                self.with_variable(name, tree.line, |variable| {
+                    if variable.constant && variable.value.is_some() {
+                        return error(tree.line, format!("cannot assign to constant '{name}'"));
+                    }
+                    if !accepts(&generics, &value, &variable.type_name) {
+                        return error(tree.line, format!("expected {} for '{name}', found {}", variable.type_name, value.type_name()));
+                    }
+                    variable.value = Some(value);
+                    Ok(())
+                })
+            },
+            TreeType::Expression => self.expression(tree).map(|_| ()),
+            TreeType::If if tree.params[0].value == TreeType::Let => {
+                let name = leaf_symbol(&tree.params[0].params[0]);
+                match self.expression(&tree.params[0].params[1])? {
+                    // the unwrapped value is a constant in a scope of its own around the body's
+                    Value::None => match tree.params.get(2) {
+                        Some(other) if other.value == TreeType::If => self.statement(other),
+                        Some(other) => self.body(other),
+                        None => Ok(()),
+                    },
+                    value => {
+                        self.locals.push_scope();
+                        self.locals.insert(name, local(Variable{
+                            type_name: value.type_token(),
+                            value: Some(value),
+                            constant: true,
+                        }));
+                        let result = self.body(&tree.params[1]);
+                        self.locals.pop_scope();
+                        result
+                    },
+                }
+            },
+            TreeType::If => {
+                if self.condition(&tree.params[0])? {
+                    self.body(&tree.params[1])
+                }
+                else {
+                    match tree.params.get(2) {
+                        Some(other) if other.value == TreeType::If => self.statement(other),
+                        Some(other) => self.body(other),
+                        None => Ok(()),
+                    }
+                }
+            },
+            TreeType::While | TreeType::For | TreeType::Forever => self.run_loop(tree, None),
+            // errors from the body, whether thrown or not, are caught, but not exits
+            TreeType::Try => match self.body(&tree.params[0]) {
+                Err(Unwind::Error(error)) => {
+                    let value = error_value(&error);
+                    self.locals.push_scope();
+                    self.locals.insert(leaf_symbol(&tree.params[1]), local(Variable{
+                        type_name: value.type_token(),
+                        value: Some(value),
+                        constant: true,
+                    }));
+                    let result = self.body(&tree.params[2]);
+                    self.locals.pop_scope();
+                    result
+                },
+                result => result,
+            },
+            TreeType::Throw => match self.expression(&tree.params[0])? {
+                Value::String(message) => error(tree.line, message),
+                // a caught error keeps the line it happened on, its trace starts again from here
+                Value::Struct{ name, fields } if name.as_str() == ERROR => {
+                    let mut error = Diagnostic::new(Some(tree.line), "");
+                    for (field, value) in fields {
+                        match (field.as_str(), value) {
+                            ("message", Value::String(message)) => error.message = message,
+                            ("line", Value::Int(line)) => error.line = usize::try_from(line).ok(),
+                            _ => {},
+                        }
+                    }
+                    Err(Unwind::Error(error))
+                },
+                other => error(tree.line, format!("expected string or Error for throw, found {}", other.type_name())),
+            },
+            TreeType::Label => self.run_loop(&tree.params[1], Some(leaf_symbol(&tree.params[0]))),
+            TreeType::Break => Err(Unwind::Break(tree.params.first().map(leaf_symbol))),
+            TreeType::Continue => Err(Unwind::Continue(tree.params.first().map(leaf_symbol))),
+            TreeType::Return => {
+                let value = match tree.params.first() {
+                    Some(exp) => self.expression(exp)?,
+                    None => Value::Void,
+                };
+                Err(Unwind::Return(value))
+            },
+            TreeType::Switch => {
+                let value = self.expression(&tree.params[0])?;
+                for arm in &tree.params[1..] {
+                    // names bound by the patterns are visible to the guard and the body
+                    self.locals.push_scope();
+                    let result = self.arm(arm, &value);
+                    self.locals.pop_scope();
+                    if result? {
+                        break;
+                    }
+                }
+                Ok(())
+            },
+            TreeType::Function => error(tree.line, "functions can only be declared at the top level"),
+            _ => error(tree.line, format!("cannot run {:?}", tree.value)),
+        }
+    }
+
+    /// runs a While, For or Forever tree, stopping at a break (or skipping to the next iteration at
+    /// a continue) that is unlabeled or has the loop's label
+    fn run_loop(&mut self, tree: &Tree, label: Option<Symbol>) -> Flow<()> {
+        // whether the loop should stop after running its body, given how the body ended
+        let stop = |result: Flow<()>| match result {
+            Err(Unwind::Break(target)) if target.is_none() || target == label => Ok(true),
+            Err(Unwind::Continue(target)) if target.is_none() || target == label => Ok(false),
+            result => result.map(|_| false),
+        };
+        match tree.value {
+            TreeType::While => {
+                while self.condition(&tree.params[0])? {
+                    if stop(self.body(&tree.params[1]))? {
+                        break;
+                    }
+                }
+            },
+            TreeType::Forever => {
+                while !stop(self.body(&tree.params[0]))? {}
+            },
+            TreeType::For => {
+                let name = leaf_symbol(&tree.params[0]);
+                let values = self.iterable(&tree.params[1])?;
+                // the loop variable is a constant in a scope of its own around the body's
+                self.locals.push_scope();
+                let mut result = Ok(());
+                for value in values {
+                    // a new variable every iteration, so lambdas capture the value from their own iteration
+                    self.locals.insert(name, local(Variable{
+                        type_name: value.type_token(),
+                        value: Some(value),
+                        constant: true,
+                    }));
+                    match stop(self.body(&tree.params[2])) {
+                        Ok(false) => {},
+                        other => {
+                            result = other.map(|_| ());
+                            break;
+                        },
+                    }
+                }
+                self.locals.pop_scope();
+                result?;
+            },
+            ref other => return error(tree.line, format!("cannot loop over {other:?}")),
+        }
+        Ok(())
+    }
+
+    /// the values that a for loop goes through, from a Range or an array or string Expression
+    fn iterable(&mut self, tree: &Tree) -> Flow<Box<dyn Iterator<Item = Value>>> {
+        if let TreeType::Range(op) = &tree.value {
+            let mut bound = |exp: &Tree| match self.expression(exp)? {
+                Value::Int(int) => Ok(int),
+                other => error(exp.line, format!("expected int for range bound, found {}", other.type_name())),
+            };
+            let (start, end) = (bound(&tree.params[0])?, bound(&tree.params[1])?);
+            return Ok(match op {
+                TokenType::RangeInclusive => Box::new((start..=end).map(Value::Int)),
+                _ => Box::new((start..end).map(Value::Int)),
+            });
+        }
+        match self.expression(tree)? {
+            Value::Array(values) => Ok(Box::new(values.into_iter())),
+            Value::String(string) => {
+                let chars: Vec<Value> = string.chars().map(|x| Value::String(x.to_string())).collect();
+                Ok(Box::new(chars.into_iter()))
+            },
+            other => error(tree.line, format!("cannot iterate over {}", other.type_name())),
+        }
+    }
+
+    /// runs the body of an Arm if one of its patterns matches value and its guard allows it,
+    /// returning whether it did
+    fn arm(&mut self, arm: &Tree, value: &Value) -> Flow<bool> {
+        let mut matched = false;
+        for pattern in &arm.params[0].params {
+            if self.pattern(pattern, value)? {
+                matched = true;
+                break;
+            }
+        }
+        if !matched {
+            return Ok(false);
+        }
+        if let Some(guard) = arm.params.get(2)
+            && !self.condition(guard)? {
+            return Ok(false);
+        }
+        self.body(&arm.params[1])?;
+        Ok(true)
+    }
+
+    /// whether value matches pattern, declaring the names the pattern binds if it does
+    fn pattern(&mut self, pattern: &Tree, value: &Value) -> Flow<bool> {
+        match &pattern.value {
+            TreeType::Leaf(TokenType::Identifier(name)) => {
+                if name.as_str() != "_" {
+                    self.locals.insert(*name, local(Variable{
+                        value: Some(value.clone()),
+                        constant: true,
+                        type_name: value.type_token(),
+                    }));
+                }
+                Ok(true)
+            },
+            TreeType::Leaf(_) => Ok(self.expression(pattern)? == *value),
+            TreeType::Range(op) => {
+                let low = self.expression(&pattern.params[0])?;
+                let high = self.expression(&pattern.params[1])?;
+                let ordering = |bound: &Value| match (value, bound) {
+                    (Value::Int(a), Value::Int(b)) => a.partial_cmp(b),
+                    (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+                    (Value::String(a), Value::String(b)) => a.partial_cmp(b),
+                    _ => None,
+                };
+                Ok(ordering(&low).is_some_and(|x| x.is_ge()) && ordering(&high).is_some_and(|x| match op {
+                    TokenType::RangeInclusive => x.is_le(),
+                    _ => x.is_lt(),
+                }))
+            },
+            TreeType::VariantPattern => {
+                let enum_name = leaf_symbol(&pattern.params[0]);
+                let variant = leaf_symbol(&pattern.params[1]);
+                let declaration = match self.modules[self.module].enums.get(&enum_name) {
+                    Some(declaration) => declaration.clone(),
+                    None => return error(pattern.line, format!("unknown enum '{enum_name}'")),
+                };
+                let field_count = match declaration.params[1..].iter().find(|x| leaf_symbol(&x.params[0]) == variant) {
+                    Some(found) => found.params.len() - 1,
+                    None => return error(pattern.line, format!("'{enum_name}' has no variant '{variant}'")),
+                };
+                // a variant without field patterns matches whatever its fields are
+                let field_patterns = &pattern.params[2..];
+                if !field_patterns.is_empty() && field_patterns.len() != field_count {
+                    return error(pattern.line, format!("'{enum_name}.{variant}' has {field_count} fields but the pattern has {}",
+                        field_patterns.len()));
+                }
+                match value {
+                    Value::Enum{ name, variant: found, fields } if *name == enum_name && *found == variant => {
+                        for (field_pattern, field) in field_patterns.iter().zip(fields) {
+                            if !self.pattern(field_pattern, field)? {
+                                return Ok(false);
+                            }
+                        }
+                        Ok(true)
+                    },
+                    _ => Ok(false),
+                }
+            },
+            other => error(pattern.line, format!("expected pattern, found {other:?}")),
+        }
+    }
+
+    /// declares a variable in the current scope, or as a global of the current module
+    fn assign(&mut self, tree: &Tree, global: bool) -> Flow<()> {
+        let name = leaf_symbol(&tree.params[1]);
+        let value = match tree.params.get(3) {
+            Some(exp) => Some(self.expression(exp)?),
+            None => None,
+        };
+        // without a type the variable has the type of its first value
+        let type_name = match (&tree.params[2].value, &value) {
+            (TreeType::Inferred, Some(Value::Void)) => return error(tree.line, format!("'{name}' cannot be void")),
+            (TreeType::Inferred, Some(value)) => value.type_token(),
+            (TreeType::Inferred, None) => return error(tree.line, format!("'{name}' needs a type or a value")),
+            _ => type_token(&tree.params[2]).clone(),
+        };
+        if let Some(value) = &value
+            && !accepts(&self.generics, value, &type_name) {
+            return error(tree.line, format!("expected {type_name} for '{name}', found {}", value.type_name()));
+        }
+        let variable = Variable{
+            value,
+            constant: matches!(tree.params[0].value, TreeType::Leaf(TokenType::Const)),
+            type_name,
+        };
+        let redeclared = match global {
+            true => self.modules[self.module].globals.insert(name, variable).is_some(),
+            false => self.locals.in_current_scope(name) || self.locals.insert(name, local(variable)).is_some(),
+        };
+        if redeclared {
+            return error(tree.line, format!("'{name}' is already declared"));
+        }
+        Ok(())
+    }
+
+    /// evaluates an if or while condition, which has to be a bool
+    fn condition(&mut self, exp: &Tree) -> Flow<bool> {
+        match self.expression(exp)? {
+            Value::Bool(value) => Ok(value),
+            other => error(exp.line, format!("expected bool for condition, found {}", other.type_name())),
+        }
+    }
+
+    fn expression(&mut self, tree: &Tree) -> Flow<Value> {
+        match &tree.value {
+            TreeType::Expression => self.expression(&tree.params[0]),
+            TreeType::Leaf(token) => match token {
+                TokenType::IntLit(val) => Ok(Value::Int(*val)),
+                TokenType::FloatLit(val) => Ok(Value::Float(*val)),
+                TokenType::BoolLit(val) => Ok(Value::Bool(*val)),
+                TokenType::StringLit(val) => Ok(Value::String(val.clone())),
+                TokenType::NoneLit => Ok(Value::None),
+                TokenType::Identifier(name) => {
+                    // a function is only used as a value if there is no variable with its name
+                    let module = &self.modules[self.module];
+                    if self.locals.get(*name).is_none() && !module.globals.contains_key(name)
+                        && let Some(function) = module.functions.get(name) {
+                        return Ok(Value::Function(declared(function.clone(), self.module)));
+                    }
+                    self.with_variable(*name, tree.line, |variable| match &variable.value {
+                        Some(value) => Ok(value.clone()),
+                        None => error(tree.line, format!("'{name}' is used before being assigned")),
+                    })
+                },
+                token => error(tree.line, format!("expected expression, found '{token}'")),
+            },
+            TreeType::Member => {
+                if let Some(declaration) = self.enum_declaration(&tree.params[0]) {
+                    return self.variant(&declaration, leaf_symbol(&tree.params[1]), Vec::new(), tree.line);
+                }
+                if !self.is_module(&tree.params[0]) {
+                    let value = self.expression(&tree.params[0])?;
+                    return self.field(value, leaf_symbol(&tree.params[1]), tree.line);
+                }
+                let (module, name) = self.member(tree)?;
+                match self.modules[module].globals.get(&name).map(|x| &x.value) {
+                    Some(Some(value)) => Ok(value.clone()),
+                    Some(None) => error(tree.line, format!("'{name}' is used before being assigned")),
+                    None => match self.modules[module].functions.get(&name) {
+                        Some(function) => Ok(Value::Function(declared(function.clone(), module))),
+                        None => error(tree.line, format!("'{name}' is a type, not a value")),
+                    },
+                }
+            },
+            // a lambda shares the locals it can see with the scope it was created in
+            TreeType::Lambda => Ok(Value::Function(Function{
+                tree: Rc::new(tree.clone()),
+                module: self.module,
+                captures: Rc::new(self.locals.visible().map(|(name, local)| (name, local.clone())).collect()),
+                generics: self.generics.clone(),
+            })),
+            TreeType::Array => {
+                let mut values = Vec::with_capacity(tree.params.len());
+                for element in &tree.params {
+                    values.push(self.expression(element)?);
+                }
+                Ok(Value::Array(values))
+            },
+            TreeType::UnaryOp(op) => {
+                let operand = self.expression(&tree.params[0])?;
+                value::unary_op(op, operand).or_else(|message| error(tree.line, message))
+            },
+            // && and || only evaluate their right hand side if they need to
+            TreeType::BinaryOp(op @ (TokenType::And | TokenType::Or)) => {
+                let lhs = match self.expression(&tree.params[0])? {
+                    Value::Bool(lhs) => lhs,
+                    other => return error(tree.line, format!("cannot apply '{op}' to {}", other.type_name())),
+                };
+                if lhs == matches!(op, TokenType::Or) {
+                    return Ok(Value::Bool(lhs));
+                }
+                match self.expression(&tree.params[1])? {
+                    Value::Bool(rhs) => Ok(Value::Bool(rhs)),
+                    other => error(tree.line, format!("cannot apply '{op}' to {}", other.type_name())),
+                }
+            },
+            // so does ??, which only needs it when the left is none
+            TreeType::BinaryOp(TokenType::Coalesce) => match self.expression(&tree.params[0])? {
+                Value::None => self.expression(&tree.params[1]),
+                lhs => Ok(lhs),
+            },
+            TreeType::BinaryOp(op) => {
+                let lhs = self.expression(&tree.params[0])?;
+                let rhs = self.expression(&tree.params[1])?;
+                value::binary_op(op, lhs, rhs).or_else(|message| error(tree.line, message))
+            },
+            TreeType::Call => {
+                let callee = &tree.params[0];
+                let mut args = Vec::with_capacity(tree.params[1].params.len());
+                for arg in &tree.params[1].params {
+                    args.push(self.expression(arg)?);
+                }
+                match &callee.value {
+                    // a variable holding a function shadows functions with the same name
+                    TreeType::Leaf(TokenType::Identifier(name)) => {
+                        let variable = match self.locals.get(*name) {
+                            Some(local) => local.borrow().value.clone(),
+                            None => self.modules[self.module].globals.get(name).and_then(|x| x.value.clone()),
+                        };
+                        let module = &self.modules[self.module];
+                        match variable {
+                            Some(Value::Function(function)) => self.call_function(&function, args, tree.line),
+                            _ if !module.functions.contains_key(name) && let Some(declaration) = module.structs.get(name) => {
+                                self.construct(&declaration.clone(), args, tree.line)
+                            },
+                            _ => self.call_named(*name, args, tree.line),
+                        }
+                    },
+                    TreeType::Member if self.enum_declaration(&callee.params[0]).is_some() => {
+                        let declaration = self.enum_declaration(&callee.params[0]).unwrap();
+                        self.variant(&declaration, leaf_symbol(&callee.params[1]), args, tree.line)
+                    },
+                    TreeType::Member if self.is_module(&callee.params[0]) => {
+                        let (module, name) = self.member(callee)?;
+                        if let Some(function) = self.modules[module].functions.get(&name) {
+                            let function = declared(function.clone(), module);
+                            return self.call_function(&function, args, tree.line);
+                        }
+                        match self.modules[module].structs.get(&name) {
+                            Some(declaration) => self.construct(&declaration.clone(), args, tree.line),
+                            None => error(tree.line, format!("'{name}' is a variable, not a function")),
+                        }
+                    },
+                    _ => match self.expression(callee)? {
+                        Value::Function(function) => self.call_function(&function, args, tree.line),
+                        other => error(tree.line, format!("cannot call {}", other.type_name())),
+                    },
+                }
+            },
+            other => error(tree.line, format!("expected expression, found {other:?}")),
+        }
+    }
+}
+
+/// the token of a leaf, which the parser guarantees trees like names and types are
+fn leaf_token(tree: &Tree) -> &TokenType {
+    match &tree.value {
+        TreeType::Leaf(token) => token,
+        other => panic!("({}) expected leaf, found {other:?}", tree.line),
+    }
+}
+
+/// the token that values of a type tree have to match, a leaf, FunctionType, GenericType or OptionalType
+fn type_token(tree: &Tree) -> &TokenType {
+    match &tree.value {
+        TreeType::FunctionType => &TokenType::Function,
+        TreeType::OptionalType => &TokenType::Question,
+        // values only know the name of their type, not what it was applied to
+        TreeType::GenericType => leaf_token(&tree.params[0]),
+        _ => leaf_token(tree),
+    }
+}
+
+/// whether value can be stored somewhere declared with type_name, which any value can be if it
+/// is one of generics
+fn accepts(generics: &[Symbol], value: &Value, type_name: &TokenType) -> bool {
+    matches!(type_name, TokenType::Identifier(name) if generics.contains(name)) || value.has_type(type_name)
+}
+
+/// the Error struct a catch binds for error
+fn error_value(error: &Diagnostic) -> Value {
+    let trace = error.trace.iter().map(|x| Value::String(x.to_string())).collect();
+    Value::Struct{
+        name: Symbol::intern(ERROR),
+        fields: vec![
+            (Symbol::intern("message"), Value::String(error.message.clone())),
+            (Symbol::intern("line"), Value::Int(error.line.unwrap_or(0) as i64)),
+            (Symbol::intern("trace"), Value::Array(trace)),
+        ],
+    }
+}
+
+/// a new local variable, not yet captured by anything
+fn local(variable: Variable) -> Local {
+    Rc::new(RefCell::new(variable))
+}
+
+/// a function declared at the top level of module as a value
+fn declared(tree: Rc<Tree>, module: usize) -> Function {
+    let generics: Rc<[Symbol]> = match tree.params.get(4) {
+        Some(type_params) => type_params.params.iter().map(leaf_symbol).collect(),
+        None => Rc::new([]),
+    };
+    Function{ tree, module, captures: Rc::default(), generics }
+}
+
+/// the name in an Identifier leaf
+fn leaf_symbol(tree: &Tree) -> Symbol {
+    match leaf_token(tree) {
+        TokenType::Identifier(name) => *name,
+        other => panic!("({}) expected identifier, found '{other}'", tree.line),
+    }
+}
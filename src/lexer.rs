@@ -1,35 +1,107 @@
-pub fn lex(input: String) -> Vec<Token> {
+use std::borrow::Cow;
+
+use crate::symbol::Symbol;
+
+pub fn lex(input: &str) -> Vec<Token> {
+    lex_with_comments(input).0
+}
+
+/// same as lex, but also returns the comments that were skipped, in order
+pub fn lex_with_comments(input: &str) -> (Vec<Token>, Vec<Comment>) {
     // println!("lexing: {input}");
-    let mut tokens: Vec<Token> = Vec::new();
-    let mut chars = input.chars().peekable();
-    let mut line_number = 1; // for displaying line number in errors
-    while chars.peek().is_some() {
-        // println!();
-        if let Some(new_token) = get_token(&mut chars, &mut line_number) {
-            // println!("token: {:?}", &new_token);
-            tokens.push(new_token);
+    let mut lexer = Lexer::new(input);
+    let tokens: Vec<Token> = lexer.by_ref().map(Token::into_owned).collect();
+    (tokens, lexer.comments)
+}
+
+/// lossless lexing, every byte of input ends up in exactly one token's text or trivia, so the
+/// input can be rebuilt from the result
+/// returns the tokens along with the trivia after the last token
+pub fn lex_lossless(input: &str) -> (Vec<TriviaToken>, Vec<Trivia>) {
+    let tokens = lex(input);
+    let mut lossless: Vec<TriviaToken> = Vec::with_capacity(tokens.len());
+    let mut prev_end = 0;
+    for token in tokens {
+        lossless.push(TriviaToken{
+            leading: split_trivia(&input[prev_end..token.start]),
+            text: input[token.start..token.end].to_string(),
+            token: token.clone(),
+        });
+        prev_end = token.end;
+    }
+    (lossless, split_trivia(&input[prev_end..]))
+}
+
+/// splits the text between two tokens into runs of whitespace and comments
+fn split_trivia(mut text: &str) -> Vec<Trivia> {
+    let mut trivia: Vec<Trivia> = Vec::new();
+    while !text.is_empty() {
+        if text.starts_with("//") {
+            let len = text.find('\n').unwrap_or(text.len());
+            trivia.push(Trivia::Comment(text[..len].to_string()));
+            text = &text[len..];
+        }
+        else {
+            let len = text.find("//").unwrap_or(text.len());
+            trivia.push(Trivia::Whitespace(text[..len].to_string()));
+            text = &text[len..];
         }
     }
-    tokens
+    trivia
+}
+
+/// streams tokens out of input, string literals without escape sequences borrow their text from
+/// it rather than being copied, and identifiers are interned without copying once their name has
+/// been seen
+pub struct Lexer<'a> {
+    input: &'a str,
+    offset: usize, // byte offset of the next char
+    line_number: usize, // for displaying line number in errors
+    pub comments: Vec<Comment>, // every comment skipped so far
 }
 
-fn get_token(chars: &mut std::iter::Peekable<std::str::Chars>, line_number: &mut usize) -> Option<Token> {
-    chars.peek()?; // return none if nothing left if no chars left
-    let mut word = String::new();
-    if chars.peek().unwrap().is_whitespace() { // skip whitespace
-        // println!("WS");
-        if chars.peek().unwrap() == &'\n' {
-            *line_number += 1;
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Lexer::starting_at(input, 0, 1)
+    }
+
+    /// a lexer that starts from byte offset start, which must be between two tokens and on the given line
+    pub fn starting_at(input: &'a str, start: usize, line_number: usize) -> Self {
+        Lexer{
+            input,
+            offset: start,
+            line_number,
+            comments: Vec::new(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.offset..].chars().next()
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let next = self.peek()?;
+        self.offset += next.len_utf8();
+        Some(next)
+    }
+
+    /// skips every char for which f is true, returning the text skipped
+    fn skip_while(&mut self, f: impl Fn(&char) -> bool) -> &'a str {
+        let start = self.offset;
+        while self.peek().is_some_and(|x| f(&x)) {
+            self.next_char();
         }
-        chars.next();
-        return None;
+        &self.input[start..self.offset]
     }
 
-    if !is_good_char(chars.peek().unwrap()) { // check if next token is not a literal/identifier
-        // println!("bad char: {}", chars.peek().unwrap());
-        return Some(Token{
-            line: *line_number,
-            value: match chars.next().unwrap() {
+    /// reads the token starting at the next char, which must not be whitespace or a comment
+    fn get_token(&mut self) -> Token<Cow<'a, str>> {
+        let start = self.offset;
+        let line_number = self.line_number;
+        let next = self.next_char().unwrap();
+        if !is_good_char(&next) { // check if next token is not a literal/identifier
+            // println!("bad char: {next}");
+            let value = match next {
                 '(' => TokenType::LeftParen,
                 ')' => TokenType::RightParen,
                 '[' => TokenType::LeftBrack,
@@ -38,16 +110,18 @@ fn get_token(chars: &mut std::iter::Peekable<std::str::Chars>, line_number: &mut
                 '}' => TokenType::RightCurly,
                 ',' => TokenType::Comma,
                 '.' => {
-                    if chars.peek().is_some_and(|x| x.is_numeric()) {
+                    if self.peek().is_some_and(|x| x.is_numeric()) {
                         // this is copied from the section handling floats but idc
-                        word.push('.');
-                        while chars.peek().is_some_and(is_good_char) {
-                            word.push(chars.next().unwrap());
-                        }
+                        self.skip_while(is_good_char);
+                        let word = &self.input[start..self.offset];
                         let flt: f64 = word.parse().unwrap_or_else(|_|
                             panic!("({line_number}) invalid float literal: {word}"));
                         TokenType::FloatLit(flt)
                     }
+                    else if self.peek() == Some('.') {
+                        self.next_char();
+                        self.lex_pair('=', TokenType::RangeInclusive, TokenType::Range)
+                    }
                     else {
                         TokenType::Period
                     }
@@ -55,104 +129,207 @@ fn get_token(chars: &mut std::iter::Peekable<std::str::Chars>, line_number: &mut
                 ':' => TokenType::Colon,
                 ';' => TokenType::Semicolon,
                 '\'' => TokenType::SQuote,
-                '\"' => lex_string(chars, line_number), // string literal
+                '\"' => self.lex_string(), // string literal
                 '\\' => TokenType::Backslash,
                 '+' => TokenType::Plus,
-                '-' => if chars.peek().is_some_and(|x| x == &'>') { chars.next(); TokenType::Arrow }
-                else { TokenType::Minus },
+                '-' => self.lex_pair('>', TokenType::Arrow, TokenType::Minus),
                 '*' => TokenType::Star,
-                '/' => TokenType::Slash, // check for comments, skip until newline
+                '/' => TokenType::Slash, // comments are skipped before getting here
                 '%' => TokenType::Mod,
                 '~' => TokenType::BwNot,
-                '|' => if chars.peek().is_some_and(|x| x == &'|') { chars.next(); TokenType::Or }
-                else { TokenType::BwOr }
-                '&' => if chars.peek().is_some_and(|x| x == &'&') { chars.next(); TokenType::And }
-                else { TokenType::BwAnd }
-                '^' => if chars.peek().is_some_and(|x| x == &'^') { chars.next(); TokenType::Xor }
-                else { TokenType::BwXor }
-                '!' => if chars.peek().is_some_and(|x| x == &'=') { chars.next(); TokenType::Neq }
-                else { TokenType::Not }
-                '=' => if chars.peek().is_some_and(|x| x == &'=') { chars.next(); TokenType::Eq }
-                else { TokenType::Assign }
-                '<' => if chars.peek().is_some_and(|x| x == &'=') { chars.next(); TokenType::Lte }
-                else { TokenType::Lt }
-                '>' => if chars.peek().is_some_and(|x| x == &'=') { chars.next(); TokenType::Gte }
-                else { TokenType::Gt }
+                '|' => self.lex_pair('|', TokenType::Or, TokenType::BwOr),
+                '&' => self.lex_pair('&', TokenType::And, TokenType::BwAnd),
+                '^' => self.lex_pair('^', TokenType::Xor, TokenType::BwXor),
+                '!' => self.lex_pair('=', TokenType::Neq, TokenType::Not),
+                '=' => self.lex_pair('=', TokenType::Eq, TokenType::Assign),
+                '<' if self.peek() == Some('<') => {
+                    self.next_char();
+                    TokenType::ShiftLeft
+                },
+                '>' if self.peek() == Some('>') => {
+                    self.next_char();
+                    TokenType::ShiftRight
+                },
+                '<' => self.lex_pair('=', TokenType::Lte, TokenType::Lt),
+                '>' => self.lex_pair('=', TokenType::Gte, TokenType::Gt),
+                '?' => self.lex_pair('?', TokenType::Coalesce, TokenType::Question),
                 other => {
-                    panic!("unrecognized character: {other}")
+                    panic!("({line_number}) unrecognized character: {other}")
                 }
-            }
-        });
-    }
+            };
+            return Token{
+                line: line_number,
+                start,
+                end: self.offset,
+                value,
+            };
+        }
 
-    // if next token is a literal/identifier/keyword, read the whole next word
-    while chars.peek().is_some_and(is_good_char) {
-        word.push(chars.next().unwrap());
-    }
+        // if next token is a literal/identifier/keyword, read the whole next word
+        self.skip_while(is_good_char);
+        let word = &self.input[start..self.offset];
 
-    // println!("word: {word}");
+        // println!("word: {word}");
 
-    // check for keywords
-    let new_token_type: Option<TokenType> = match word.as_str() {
-        "const" => Some(TokenType::Const),
-        "var" => Some(TokenType::Var),
-        "true" => Some(TokenType::BoolLit(true)),
-        "false" => Some(TokenType::BoolLit(false)),
-        "int" => Some(TokenType::Int),
-        "float" => Some(TokenType::Float),
-        "bool" => Some(TokenType::Bool),
-        "string" => Some(TokenType::String),
-        "enum" => Some(TokenType::Enum),
-        "struct" => Some(TokenType::Struct),
-        "fn" => Some(TokenType::Function),
-        "if" => Some(TokenType::If),
-        "else" => Some(TokenType::Else),
-        "while" => Some(TokenType::While),
-        "return" => Some(TokenType::Return),
-        _ => None,
-    };
-    if new_token_type.is_some() {
-        return Some(Token{
-            line: *line_number,
-            value: new_token_type.unwrap(),
-        });
-    }
+        // check for keywords
+        let new_token_type: Option<TokenType<Cow<'a, str>>> = match word {
+            "const" => Some(TokenType::Const),
+            "var" => Some(TokenType::Var),
+            "true" => Some(TokenType::BoolLit(true)),
+            "false" => Some(TokenType::BoolLit(false)),
+            "none" => Some(TokenType::NoneLit),
+            "let" => Some(TokenType::Let),
+            "int" => Some(TokenType::Int),
+            "float" => Some(TokenType::Float),
+            "bool" => Some(TokenType::Bool),
+            "string" => Some(TokenType::String),
+            "enum" => Some(TokenType::Enum),
+            "struct" => Some(TokenType::Struct),
+            "fn" => Some(TokenType::Function),
+            "if" => Some(TokenType::If),
+            "else" => Some(TokenType::Else),
+            "while" => Some(TokenType::While),
+            "switch" => Some(TokenType::Switch),
+            "for" => Some(TokenType::For),
+            "in" => Some(TokenType::In),
+            "forever" => Some(TokenType::Forever),
+            "return" => Some(TokenType::Return),
+            "defer" => Some(TokenType::Defer),
+            "try" => Some(TokenType::Try),
+            "catch" => Some(TokenType::Catch),
+            "throw" => Some(TokenType::Throw),
+            "break" => Some(TokenType::Break),
+            "continue" => Some(TokenType::Continue),
+            "import" => Some(TokenType::Import),
+            "pub" => Some(TokenType::Pub),
+            _ => None,
+        };
+        if let Some(new_token_type) = new_token_type {
+            return Token{
+                line: line_number,
+                start,
+                end: self.offset,
+                value: new_token_type,
+            };
+        }
 
-    // check for ints/floats
-    // dont immediately return number vals if word is a number so that the
-    let mut is_float = false; // invalid literal message can display the full literal
-    if word.chars().next().unwrap().is_numeric() {
-        if chars.peek().is_some_and(|x| x == &'.') {
-            is_float = true;
-            chars.next();
-            word.push('.');
-            while chars.peek().is_some_and(is_good_char) {
-                word.push(chars.next().unwrap());
+        // check for ints/floats
+        if next.is_numeric() {
+            // a '.' followed by another is a range, e.g. `0..10`, rather than part of a float
+            if self.peek() == Some('.') && !self.input[self.offset + 1..].starts_with('.') { // float found
+                self.next_char();
+                self.skip_while(is_good_char);
+                // the full literal is used so that the invalid literal message can display it
+                let word = &self.input[start..self.offset];
+                let flt: f64 = word.parse().unwrap_or_else(|_|
+                    panic!("({line_number}) invalid float literal: {word}"));
+                return Token{
+                    line: line_number,
+                    start,
+                    end: self.offset,
+                    value: TokenType::FloatLit(flt),
+                };
+            }
+            else { // int found
+                let int: i64 = word.parse().unwrap_or_else(|_|
+                    panic!("({line_number}) invalid integer literal: {word}"));
+                return Token{
+                    line: line_number,
+                    start,
+                    end: self.offset,
+                    value: TokenType::IntLit(int),
+                };
             }
         }
-        if is_float { // float found
-            let flt: f64 = word.parse().unwrap_or_else(|_|
-                panic!("({line_number}) invalid float literal: {word}"));
-            return Some(Token{
-                line: *line_number,
-                value: TokenType::FloatLit(flt),
-            });
+
+        // must be identifier
+        Token{
+            line: line_number,
+            start,
+            end: self.offset,
+            value: TokenType::Identifier(Symbol::intern(word)),
+        }
+    }
+
+    /// reads a second char if it is second, for two char tokens that start with a valid one char token
+    fn lex_pair(&mut self, second: char, pair: TokenType<Cow<'a, str>>, single: TokenType<Cow<'a, str>>) -> TokenType<Cow<'a, str>> {
+        if self.peek() == Some(second) {
+            self.next_char();
+            pair
         }
-        else { // int found
-            let int: i64 = word.parse().unwrap_or_else(|_|
-                panic!("({line_number}) invalid integer literal: {word}"));
-            return Some(Token{
-                line: *line_number,
-                value: TokenType::IntLit(int)
-            });
+        else {
+            single
         }
     }
 
-    // must be identifier
-    Some(Token{
-        line: *line_number,
-        value: TokenType::Identifier(word)
-    })
+    /// reads the rest of a string literal, after its opening '"'
+    fn lex_string(&mut self) -> TokenType<Cow<'a, str>> {
+        let start = self.offset;
+        // only strings with escape sequences need their own copy
+        let mut output_string: Option<String> = None;
+        loop {
+            let char_start = self.offset;
+            match self.next_char() {
+                Some('\"') => { // end of string
+                    let string = match output_string {
+                        Some(string) => Cow::Owned(string),
+                        None => Cow::Borrowed(&self.input[start..char_start]),
+                    };
+                    return TokenType::StringLit(string);
+                }
+                Some('\\') => { // esc char found
+                    let output_string = output_string.get_or_insert_with(|| self.input[start..char_start].to_string());
+                    match self.next_char() {
+                        Some('t') => { output_string.push('\t') }
+                        Some('n') => { output_string.push('\n') }
+                        Some('\\') => { output_string.push('\\') }
+                        Some('"') => { output_string.push('\"') }
+                        Some(esc_char) => {
+                            panic!("({}) invalid escape sequence: '\\{esc_char}'", self.line_number)
+                        }
+                        None => break, // file ends before esc seq does
+                    }
+                }
+                Some(char) => {
+                    if char == '\n' { self.line_number += 1; }
+                    if let Some(output_string) = &mut output_string {
+                        output_string.push(char)
+                    }
+                }, // unremarkable char found
+                None => break, // file ends before string does
+            }
+        }
+        let output_string = output_string.unwrap_or_else(|| self.input[start..].to_string());
+        panic!("({}) invalid string literal: \"{output_string}", self.line_number);
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token<Cow<'a, str>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let next = self.peek()?; // return none if no chars left
+            if self.input[self.offset..].starts_with("//") { // skip comments
+                let line = self.line_number;
+                let text = &self.skip_while(|x| x != &'\n')[2..];
+                self.comments.push(Comment{
+                    line,
+                    text: text.trim_end().to_string(),
+                });
+            }
+            else if next.is_whitespace() { // skip whitespace
+                // println!("WS");
+                if next == '\n' {
+                    self.line_number += 1;
+                }
+                self.next_char();
+            }
+            else {
+                return Some(self.get_token());
+            }
+        }
+    }
 }
 
 /// returns true if c is a valid character in an idenfier or a literal
@@ -163,110 +340,222 @@ fn is_good_char(c: &char) -> bool {
     c.is_alphanumeric() || other_chars.contains(c)
 }
 
-fn lex_string(chars: &mut std::iter::Peekable<std::str::Chars>, line_number: &mut usize) -> TokenType {
-    let mut output_string = String::new();
-    let mut invalid = false;
-    loop {
-        let next = chars.next();
-        match next {
-            Some(char) => {
-                match char {
-                    '\"' => { break } // end of string
-                    '\\' => { // esc char found
-                        let next = chars.next();
-                        match next {
-                            Some(esc_char) => {
-                                match esc_char {
-                                    't' => { output_string.push('\t') }
-                                    'n' => { output_string.push('\n') }
-                                    '\\' => { output_string.push('\\') }
-                                    '"' => { output_string.push('\"') }
-                                    _ => {
-                                        panic!("({line_number}) invalid escape sequence: '\\{esc_char}'")
-                                    }
-                                }
-                            },
-                            None => { // file ends before esc seq does
-                                invalid = true;
-                                break;
-                            }
-                        }
-                    }
-                    char => {
-                        if char == '\n' { *line_number += 1; }
-                        output_string.push(char)
-                    }, // unremarkable char found
-                }
-            },
-            None => { // file ends before string does
-                invalid = true;
-                break;
-            },
-        }
-    }
-    if invalid {
-        panic!("({line_number}) invalid string literal: \"{output_string}");
-    }
-    TokenType::StringLit(output_string)
-}
-
 // symbols that dont need to be separated by spaces to be counted as separate tokens
 // const SEPARATORS: [char; 28] = [' ', '\t','\n','(', ')', '[', ']', '{', '}',
 //                                 ',', '.', ';', '\'','\"','\\',
 //                                 '+', '-', '*', '/', '%',
 //                                 '~', '|', '&', '^', '!', '=', '<', '>'];
 
-#[derive(Clone)]
-pub struct Token {
+/// a `//` comment, text is everything after the slashes up to the end of the line
+#[derive(Debug, Clone)]
+pub struct Comment {
     pub line: usize,
-    pub value: TokenType,
+    pub text: String,
 }
-impl PartialEq for Token {
-    fn eq(&self, other: &Self) -> bool {
-        std::mem::discriminant(&self.value) == std::mem::discriminant(&other.value)
+
+/// text skipped over by the lexer, kept by lex_lossless
+#[derive(Debug, Clone)]
+pub enum Trivia {
+    Whitespace(String),
+    Comment(String), // includes the '//', but not the newline after it
+}
+impl Trivia {
+    pub fn text(&self) -> &str {
+        match self {
+            Trivia::Whitespace(text) | Trivia::Comment(text) => text,
+        }
+    }
+}
+
+/// a token along with the exact source text it was lexed from and the trivia before it
+#[derive(Debug, Clone)]
+pub struct TriviaToken {
+    pub leading: Vec<Trivia>,
+    pub text: String,
+    pub token: Token,
+}
+
+/// S is the type of the text in string literals, which the Lexer borrows from its input where it can
+/// two tokens are equal when both their values and their positions are
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Token<S = String> {
+    pub line: usize,
+    pub start: usize, // byte offset of the first char of the token
+    pub end: usize, // byte offset just past the last char of the token
+    pub value: TokenType<S>,
+}
+impl Token<Cow<'_, str>> {
+    pub fn into_owned(self) -> Token {
+        Token{
+            line: self.line,
+            start: self.start,
+            end: self.end,
+            value: self.value.into_owned(),
+        }
+    }
+}
+impl<S> Token<S> {
+    /// whether both tokens are the same kind of token, ignoring literal values and positions
+    pub fn same_kind(&self, other: &Self) -> bool {
+        self.value.same_kind(&other.value)
     }
 }
-impl std::fmt::Debug for Token {
+impl<S: std::fmt::Debug> std::fmt::Debug for Token<S> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self.value)
     }
 }
 #[derive(Debug, Clone)]
-pub enum TokenType {
+pub enum TokenType<S = String> {
     // delimiter
     LeftParen, RightParen,
     LeftBrack, RightBrack,
     LeftCurly, RightCurly,
     Comma, Period, Colon, Semicolon,
     SQuote, DQuote, Backslash, Arrow, // '->'
+    Question, // '?' after a type, making it optional
+    Range, RangeInclusive, // '..', '..='
     Assign, // single '='
     NewLine, // only used to tell the parser which line its currently on
     // operator
     Plus, Minus, Star, Slash, Mod,
     BwNot, BwOr, BwAnd, BwXor,
+    ShiftLeft, ShiftRight, // '<<', '>>'
     Not, Or, And, Xor,
     Eq, Lt, Gt, Neq, Lte, Gte, // Eq: '=='
+    Coalesce, // '??'
     // keyword
-    Const, Var,
+    Const, Var, Let,
     Int, Float, Bool, String, Void, // Array,
     Enum, Struct, Function,
-    If, Else, While,
+    If, Else, Switch, While, For, In, Forever, Break, Continue,
     // to be added
-    // switch, cond, import, break, continue, for, forever
-    // more tentative
-    // print, input, defer
-    Return,
+    // cond
+    // print and input are builtins, see builtins.rs
+    Return, Defer,
+    Try, Catch, Throw,
+    Import, Pub,
     // literal
-    IntLit(i64), FloatLit(f64), BoolLit(bool), StringLit(String), // no arraylit token bc hard
+    IntLit(i64), FloatLit(f64), BoolLit(bool), StringLit(S), NoneLit, // no arraylit token bc hard
     // identifier (incl variable, struct, fn, etc.)
-    Identifier(String),
+    Identifier(Symbol),
 }
-impl PartialEq for TokenType {
-    fn eq(&self, other: &Self) -> bool {
+impl TokenType<Cow<'_, str>> {
+    pub fn into_owned(self) -> TokenType {
+        match self {
+            TokenType::LeftParen => TokenType::LeftParen,
+            TokenType::RightParen => TokenType::RightParen,
+            TokenType::LeftBrack => TokenType::LeftBrack,
+            TokenType::RightBrack => TokenType::RightBrack,
+            TokenType::LeftCurly => TokenType::LeftCurly,
+            TokenType::RightCurly => TokenType::RightCurly,
+            TokenType::Comma => TokenType::Comma,
+            TokenType::Period => TokenType::Period,
+            TokenType::Colon => TokenType::Colon,
+            TokenType::Semicolon => TokenType::Semicolon,
+            TokenType::SQuote => TokenType::SQuote,
+            TokenType::DQuote => TokenType::DQuote,
+            TokenType::Backslash => TokenType::Backslash,
+            TokenType::Arrow => TokenType::Arrow,
+            TokenType::Question => TokenType::Question,
+            TokenType::Range => TokenType::Range,
+            TokenType::RangeInclusive => TokenType::RangeInclusive,
+            TokenType::Assign => TokenType::Assign,
+            TokenType::NewLine => TokenType::NewLine,
+            TokenType::Plus => TokenType::Plus,
+            TokenType::Minus => TokenType::Minus,
+            TokenType::Star => TokenType::Star,
+            TokenType::Slash => TokenType::Slash,
+            TokenType::Mod => TokenType::Mod,
+            TokenType::BwNot => TokenType::BwNot,
+            TokenType::BwOr => TokenType::BwOr,
+            TokenType::BwAnd => TokenType::BwAnd,
+            TokenType::BwXor => TokenType::BwXor,
+            TokenType::ShiftLeft => TokenType::ShiftLeft,
+            TokenType::ShiftRight => TokenType::ShiftRight,
+            TokenType::Not => TokenType::Not,
+            TokenType::Or => TokenType::Or,
+            TokenType::And => TokenType::And,
+            TokenType::Xor => TokenType::Xor,
+            TokenType::Eq => TokenType::Eq,
+            TokenType::Lt => TokenType::Lt,
+            TokenType::Gt => TokenType::Gt,
+            TokenType::Neq => TokenType::Neq,
+            TokenType::Lte => TokenType::Lte,
+            TokenType::Gte => TokenType::Gte,
+            TokenType::Coalesce => TokenType::Coalesce,
+            TokenType::Const => TokenType::Const,
+            TokenType::Var => TokenType::Var,
+            TokenType::Let => TokenType::Let,
+            TokenType::Int => TokenType::Int,
+            TokenType::Float => TokenType::Float,
+            TokenType::Bool => TokenType::Bool,
+            TokenType::String => TokenType::String,
+            TokenType::Void => TokenType::Void,
+            TokenType::Enum => TokenType::Enum,
+            TokenType::Struct => TokenType::Struct,
+            TokenType::Function => TokenType::Function,
+            TokenType::If => TokenType::If,
+            TokenType::Else => TokenType::Else,
+            TokenType::While => TokenType::While,
+            TokenType::Return => TokenType::Return,
+            TokenType::Defer => TokenType::Defer,
+            TokenType::Try => TokenType::Try,
+            TokenType::Catch => TokenType::Catch,
+            TokenType::Throw => TokenType::Throw,
+            TokenType::Switch => TokenType::Switch,
+            TokenType::For => TokenType::For,
+            TokenType::In => TokenType::In,
+            TokenType::Forever => TokenType::Forever,
+            TokenType::Break => TokenType::Break,
+            TokenType::Continue => TokenType::Continue,
+            TokenType::Import => TokenType::Import,
+            TokenType::Pub => TokenType::Pub,
+            TokenType::IntLit(val) => TokenType::IntLit(val),
+            TokenType::FloatLit(val) => TokenType::FloatLit(val),
+            TokenType::BoolLit(val) => TokenType::BoolLit(val),
+            TokenType::StringLit(val) => TokenType::StringLit(val.into_owned()),
+            TokenType::NoneLit => TokenType::NoneLit,
+            TokenType::Identifier(val) => TokenType::Identifier(val),
+        }
+    }
+}
+impl<S> TokenType<S> {
+    /// whether both are the same kind of token, e.g. any two IntLits are, whatever their values
+    pub fn same_kind(&self, other: &Self) -> bool {
         std::mem::discriminant(self) == std::mem::discriminant(other)
     }
 }
-impl std::fmt::Display for TokenType {
+// float literals are compared (and hashed) by their bits, so that equality is reflexive and agrees
+// with Hash: NaN equals a NaN with the same bits, and 0.0 does not equal -0.0
+impl<S: PartialEq> PartialEq for TokenType<S> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (TokenType::IntLit(a), TokenType::IntLit(b)) => a == b,
+            (TokenType::FloatLit(a), TokenType::FloatLit(b)) => a.to_bits() == b.to_bits(),
+            (TokenType::BoolLit(a), TokenType::BoolLit(b)) => a == b,
+            (TokenType::StringLit(a), TokenType::StringLit(b)) => a == b,
+            (TokenType::Identifier(a), TokenType::Identifier(b)) => a == b,
+            // every kind with a value is handled above, the rest are equal to their own kind
+            _ => self.same_kind(other),
+        }
+    }
+}
+impl<S: Eq> Eq for TokenType<S> {}
+impl<S: std::hash::Hash> std::hash::Hash for TokenType<S> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            TokenType::IntLit(val) => val.hash(state),
+            TokenType::FloatLit(val) => val.to_bits().hash(state),
+            TokenType::BoolLit(val) => val.hash(state),
+            TokenType::StringLit(val) => val.hash(state),
+            TokenType::Identifier(val) => val.hash(state),
+            _ => {},
+        }
+    }
+}
+impl<S: std::fmt::Display> std::fmt::Display for TokenType<S> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let string = match &self {
             TokenType::LeftParen => "(",
@@ -283,6 +572,9 @@ impl std::fmt::Display for TokenType {
             TokenType::DQuote => "\"",
             TokenType::Backslash => "\\",
             TokenType::Arrow => "->",
+            TokenType::Question => "?",
+            TokenType::Range => "..",
+            TokenType::RangeInclusive => "..=",
             TokenType::Assign => "=",
             TokenType::NewLine => "\n",
             TokenType::Plus => "+",
@@ -294,6 +586,8 @@ impl std::fmt::Display for TokenType {
             TokenType::BwOr => "|",
             TokenType::BwAnd => "&",
             TokenType::BwXor => "^",
+            TokenType::ShiftLeft => "<<",
+            TokenType::ShiftRight => ">>",
             TokenType::Not => "!",
             TokenType::Or => "||",
             TokenType::And => "&&",
@@ -304,8 +598,10 @@ impl std::fmt::Display for TokenType {
             TokenType::Neq => "!=",
             TokenType::Lte => "<=",
             TokenType::Gte => ">=",
+            TokenType::Coalesce => "??",
             TokenType::Const => "const",
             TokenType::Var => "var",
+            TokenType::Let => "let",
             TokenType::Int => "int",
             TokenType::Float => "float",
             TokenType::Bool => "bool",
@@ -318,10 +614,23 @@ impl std::fmt::Display for TokenType {
             TokenType::Else => "else",
             TokenType::While => "while",
             TokenType::Return => "return",
+            TokenType::Defer => "defer",
+            TokenType::Try => "try",
+            TokenType::Catch => "catch",
+            TokenType::Throw => "throw",
+            TokenType::Switch => "switch",
+            TokenType::For => "for",
+            TokenType::In => "in",
+            TokenType::Forever => "forever",
+            TokenType::Break => "break",
+            TokenType::Continue => "continue",
+            TokenType::Import => "import",
+            TokenType::Pub => "pub",
             TokenType::IntLit(val) => &val.to_string(),
             TokenType::FloatLit(val) => &val.to_string(),
             TokenType::BoolLit(val) => &val.to_string(),
             TokenType::StringLit(val) => &val.to_string(),
+            TokenType::NoneLit => "none",
             TokenType::Identifier(val) => &val.to_string(),
         };
         write!(f, "{string}")
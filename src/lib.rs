@@ -1,2 +1,14 @@
 pub mod lexer;
 pub mod parser;
+pub mod formatter;
+pub mod cst;
+pub mod diagnostic;
+pub mod check;
+pub mod optimize;
+pub mod types;
+pub mod incremental;
+pub mod symbol;
+pub mod value;
+pub mod builtins;
+pub mod interpreter;
+pub mod engine;
@@ -1,10 +1,19 @@
 // use std::io::Write as _;
 use std::fmt::Write as _;
+use std::path::Path;
 
-use interpreter::{parser, lexer};
+use interpreter::{builtins, check, diagnostic, formatter, interpreter as runtime, optimize, parser, lexer, types};
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
+    if args.get(1).is_some_and(|x| x == "fmt") {
+        fmt(&args[2..]);
+        return;
+    }
+    if args.get(1).is_some_and(|x| x == "run") {
+        run(&args[2..]);
+        return;
+    }
     let file_path = args.get(1).expect("input file not specified");
     let mut fnf_msg = String::new();
     write!(&mut fnf_msg, "file not found: {}", file_path).expect("file not found");
@@ -20,7 +29,7 @@ fn main() {
     println!("| Token List |");
     println!("|============|");
     println!();
-    let tokens = lexer::lex(input);
+    let tokens = lexer::lex(&input);
     let mut line = 1;
     for t in &tokens {
         if line != t.line {
@@ -43,3 +52,72 @@ fn main() {
     let tree = parser::parse(tokens);
     print!("{tree:?}");
 }
+
+/// `run <file>`: runs a script, exiting with its exit code
+fn run(args: &[String]) {
+    let file_path = args.first().expect("input file not specified");
+    let input = std::fs::read_to_string(file_path)
+        .unwrap_or_else(|_| panic!("file not found: {file_path}"));
+    let result = diagnostic::catch(|| parser::parse(lexer::lex(&input))).and_then(|mut tree| {
+        for warning in check::check(&tree) {
+            eprint!("{}", warning.render(file_path, &input));
+        }
+        // every type error is reported, the last one the same way as any other error
+        let mut errors = types::check(&tree);
+        if errors.is_empty() {
+            errors = optimize::optimize(&mut tree);
+        }
+        if let Some(last) = errors.pop() {
+            for error in errors {
+                eprint!("{}", error.render(file_path, &input));
+            }
+            return Err(last);
+        }
+        runtime::run(&tree, Path::new(file_path), builtins::Io::stdio())
+    });
+    match result {
+        Ok(code) => std::process::exit(code),
+        Err(error) => {
+            eprint!("{}", error.render(file_path, &input));
+            std::process::exit(1);
+        },
+    }
+}
+
+/// `fmt [--check] <files>`: rewrites each file in its canonical format
+/// with --check nothing is written and the exit code is 1 if any file is not formatted
+/// files that do not parse are reported and left alone, and also make the exit code 1
+fn fmt(args: &[String]) {
+    let check = args.iter().any(|x| x == "--check");
+    let file_paths: Vec<&String> = args.iter().filter(|x| *x != "--check").collect();
+    if file_paths.is_empty() {
+        panic!("input file not specified");
+    }
+    let mut failed = false;
+    for file_path in file_paths {
+        let input = std::fs::read_to_string(file_path)
+            .unwrap_or_else(|_| panic!("file not found: {file_path}"));
+        let output = match diagnostic::catch(|| formatter::format_source(&input)) {
+            Ok(output) => output,
+            Err(error) => {
+                eprintln!("{file_path}: {error}");
+                failed = true;
+                continue;
+            },
+        };
+        if output == input {
+            continue;
+        }
+        if check {
+            eprintln!("not formatted: {file_path}");
+            failed = true;
+        }
+        else {
+            std::fs::write(file_path, output)
+                .unwrap_or_else(|_| panic!("could not write file: {file_path}"));
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,347 @@
+//! an optimization pass over a file once it type checks, before it is run
+//!
+//! expressions whose operands are all literals are folded into a literal, constants whose values
+//! are literals are inlined wherever they are read, and code that can never run is removed: the
+//! bodies of `if (false)` and `while (false)` and whatever follows a return, break, continue or
+//! throw in the same body
+//!
+//! an expression that cannot be folded because evaluating it is an error is left for the
+//! interpreter to raise that error if it ever runs. the exception is integer overflow outside of a
+//! try, which is almost certainly a mistake, so it is reported as a compile error even though the
+//! code it is in might never run
+
+use crate::diagnostic::Diagnostic;
+use crate::lexer::TokenType;
+use crate::parser::{Tree, TreeType};
+use crate::symbol::{Symbol, SymbolTable};
+use crate::value::{self, OVERFLOW, Value};
+
+struct Optimizer {
+    // every name in scope, along with the literal it always has if it is a constant that can be inlined
+    constants: SymbolTable<Option<TokenType>>,
+    errors: Vec<Diagnostic>,
+    trying: usize, // how many try bodies the expression being folded is in
+}
+
+/// optimizes a File tree in place, returning the errors found while folding its expressions
+pub fn optimize(file: &mut Tree) -> Vec<Diagnostic> {
+    let mut optimizer = Optimizer{
+        constants: SymbolTable::new(),
+        errors: Vec::new(),
+        trying: 0,
+    };
+    // top level statements run in order, so a constant is only inlined into those after it
+    let mut items = Vec::with_capacity(file.params.len());
+    let mut called = false; // whether a function might have been called yet
+    let mut late = Vec::new(); // the constants declared after that
+    for item in std::mem::take(&mut file.params) {
+        if is_function(&item) {
+            items.push(item);
+            continue;
+        }
+        called = called || calls(&item);
+        if called {
+            late.extend(declared(&item));
+        }
+        items.extend(optimizer.statement(item));
+    }
+    // a function can use every constant declared before any top level statement could call it, the
+    // rest might not exist yet when it runs
+    for name in late {
+        optimizer.constants.insert(name, None);
+    }
+    for item in items.iter_mut().filter(|x| is_function(x)) {
+        let function = match item.value {
+            TreeType::Pub => &mut item.params[0],
+            _ => item,
+        };
+        let [_, params, _, body, ..] = &mut function.params[..] else {
+            unreachable!("functions have a name, parameters, a return type and a body");
+        };
+        optimizer.function(params, body);
+    }
+    file.params = items;
+    optimizer.errors
+}
+
+impl Optimizer {
+    /// the statement once it is optimized, None if it does nothing and can be removed
+    fn statement(&mut self, mut tree: Tree) -> Option<Tree> {
+        match tree.value {
+            TreeType::Assign => {
+                if let Some(value) = tree.params.get_mut(3) {
+                    self.expression(value);
+                }
+                let constant = match (&tree.params[0].value, &tree.params[2].value, tree.params.get(3).and_then(literal)) {
+                    // a constant is only inlined if its literal is exactly the type it was declared with
+                    (TreeType::Leaf(TokenType::Const), TreeType::Inferred, Some(value)) => token(value),
+                    (TreeType::Leaf(TokenType::Const), TreeType::Leaf(declared), Some(value))
+                        if *declared == value.type_token() => token(value),
+                    _ => None,
+                };
+                self.constants.insert(name(&tree.params[1]), constant);
+            },
+            TreeType::Reassign => self.expression(&mut tree.params[1]),
+            TreeType::Expression => self.expression(&mut tree),
+            TreeType::If => return self.if_statement(tree),
+            TreeType::While => {
+                self.expression(&mut tree.params[0]);
+                if literal(&tree.params[0]).is_some_and(|x| x == Value::Bool(false)) {
+                    return None;
+                }
+                self.body(&mut tree.params[1]);
+            },
+            TreeType::For => {
+                self.expression(&mut tree.params[1]);
+                self.constants.push_scope();
+                self.constants.insert(name(&tree.params[0]), None);
+                self.body(&mut tree.params[2]);
+                self.constants.pop_scope();
+            },
+            TreeType::Forever => self.body(&mut tree.params[0]),
+            // these are removed along with the statement they wrap
+            TreeType::Pub | TreeType::Label | TreeType::Defer => {
+                let inner = tree.params.pop().expect("a Pub, Label or Defer wraps a statement");
+                tree.params.push(self.statement(inner)?);
+            },
+            TreeType::Try => {
+                self.trying += 1;
+                self.body(&mut tree.params[0]);
+                self.trying -= 1;
+                self.constants.push_scope();
+                self.constants.insert(name(&tree.params[1]), None);
+                self.body(&mut tree.params[2]);
+                self.constants.pop_scope();
+            },
+            TreeType::Return | TreeType::Throw => {
+                for param in &mut tree.params {
+                    self.expression(param);
+                }
+            },
+            TreeType::Switch => {
+                let [value, arms @ ..] = &mut tree.params[..] else {
+                    unreachable!("a switch has a value");
+                };
+                self.expression(value);
+                for arm in arms {
+                    // names bound by the patterns are visible to the guard and the body
+                    self.constants.push_scope();
+                    self.bind(&arm.params[0]);
+                    if let Some(guard) = arm.params.get_mut(2) {
+                        self.expression(guard);
+                    }
+                    self.body(&mut arm.params[1]);
+                    self.constants.pop_scope();
+                }
+            },
+            _ => {},
+        }
+        Some(tree)
+    }
+
+    /// an If whose condition is a literal is replaced with the branch it always takes
+    fn if_statement(&mut self, mut tree: Tree) -> Option<Tree> {
+        if tree.params[0].value == TreeType::Let {
+            self.expression(&mut tree.params[0].params[1]);
+            self.constants.push_scope();
+            self.constants.insert(name(&tree.params[0].params[0]), None);
+            self.body(&mut tree.params[1]);
+            self.constants.pop_scope();
+            self.other(&mut tree);
+            return Some(tree);
+        }
+        self.expression(&mut tree.params[0]);
+        match literal(&tree.params[0]) {
+            Some(Value::Bool(true)) => {
+                tree.params.truncate(2);
+                self.body(&mut tree.params[1]);
+            },
+            Some(Value::Bool(false)) => {
+                let other = tree.params.drain(1..).nth(1)?;
+                if other.value == TreeType::If {
+                    return self.statement(other);
+                }
+                // the else body still needs a scope of its own, which an If that always runs it has
+                tree.params[0].params[0].value = TreeType::Leaf(TokenType::BoolLit(true));
+                tree.params.push(other);
+                self.body(&mut tree.params[1]);
+            },
+            _ => {
+                self.body(&mut tree.params[1]);
+                self.other(&mut tree);
+            },
+        }
+        Some(tree)
+    }
+
+    /// the else branch of an If, if it has one
+    fn other(&mut self, tree: &mut Tree) {
+        if tree.params.len() < 3 {
+            return;
+        }
+        let mut other = tree.params.pop().unwrap();
+        if other.value == TreeType::If {
+            tree.params.extend(self.statement(other));
+        }
+        else {
+            self.body(&mut other);
+            tree.params.push(other);
+        }
+    }
+
+    fn body(&mut self, body: &mut Tree) {
+        self.constants.push_scope();
+        let mut statements = Vec::with_capacity(body.params.len());
+        for statement in std::mem::take(&mut body.params) {
+            let Some(statement) = self.statement(statement) else {
+                continue;
+            };
+            let leaves = matches!(statement.value, TreeType::Return | TreeType::Break | TreeType::Continue | TreeType::Throw);
+            statements.push(statement);
+            // nothing after it can run
+            if leaves {
+                break;
+            }
+        }
+        body.params = statements;
+        self.constants.pop_scope();
+    }
+
+    fn function(&mut self, params: &Tree, body: &mut Tree) {
+        self.constants.push_scope();
+        for param in &params.params {
+            self.constants.insert(name(&param.params[0]), None);
+        }
+        self.body(body);
+        self.constants.pop_scope();
+    }
+
+    /// declares the names a switch pattern binds, which shadow any constants with the same names
+    fn bind(&mut self, pattern: &Tree) {
+        match pattern.value {
+            TreeType::Leaf(TokenType::Identifier(name)) => {
+                self.constants.insert(name, None);
+            },
+            TreeType::Patterns => pattern.params.iter().for_each(|x| self.bind(x)),
+            TreeType::VariantPattern => pattern.params[2..].iter().for_each(|x| self.bind(x)),
+            _ => {},
+        }
+    }
+
+    fn expression(&mut self, tree: &mut Tree) {
+        match &tree.value {
+            TreeType::Leaf(TokenType::Identifier(name)) => {
+                if let Some(Some(token)) = self.constants.get(*name) {
+                    tree.value = TreeType::Leaf(token.clone());
+                }
+            },
+            TreeType::UnaryOp(op) => {
+                let op = op.clone();
+                self.expression(&mut tree.params[0]);
+                if let Some(operand) = literal(&tree.params[0]) {
+                    self.fold(tree, value::unary_op(&op, operand));
+                }
+            },
+            // && and || only need their right hand side if their left does not decide the result
+            TreeType::BinaryOp(op @ (TokenType::And | TokenType::Or)) => {
+                let decides = matches!(op, TokenType::Or);
+                self.expression(&mut tree.params[0]);
+                self.expression(&mut tree.params[1]);
+                match literal(&tree.params[0]) {
+                    Some(Value::Bool(lhs)) if lhs == decides => *tree = tree.params.swap_remove(0),
+                    Some(Value::Bool(_)) => *tree = tree.params.swap_remove(1),
+                    _ => {},
+                }
+            },
+            TreeType::BinaryOp(TokenType::Coalesce) => tree.params.iter_mut().for_each(|x| self.expression(x)),
+            TreeType::BinaryOp(op) => {
+                let op = op.clone();
+                self.expression(&mut tree.params[0]);
+                self.expression(&mut tree.params[1]);
+                if let (Some(lhs), Some(rhs)) = (literal(&tree.params[0]), literal(&tree.params[1])) {
+                    self.fold(tree, value::binary_op(&op, lhs, rhs));
+                }
+            },
+            TreeType::Lambda => {
+                let [params, _, body] = &mut tree.params[..] else {
+                    unreachable!("lambdas have parameters, a return type and a body");
+                };
+                self.function(params, body);
+            },
+            // the name of a member is not a variable
+            TreeType::Member => self.expression(&mut tree.params[0]),
+            TreeType::Expression | TreeType::Call | TreeType::Arguments | TreeType::Array | TreeType::Range(_) => {
+                tree.params.iter_mut().for_each(|x| self.expression(x));
+            },
+            _ => {},
+        }
+    }
+
+    /// replaces tree with the literal it always evaluates to, or leaves it be if evaluating it is
+    /// an error
+    fn fold(&mut self, tree: &mut Tree, result: Result<Value, String>) {
+        match result {
+            Ok(value) => if let Some(token) = token(value) {
+                tree.value = TreeType::Leaf(token);
+                tree.params.clear();
+            },
+            Err(message) if message == OVERFLOW && self.trying == 0 => {
+                self.errors.push(Diagnostic::new(Some(tree.line), format!("{message} in constant expression")));
+            },
+            Err(_) => {},
+        }
+    }
+}
+
+/// the value of an expression that is a literal
+fn literal(tree: &Tree) -> Option<Value> {
+    match &tree.value {
+        TreeType::Expression => literal(&tree.params[0]),
+        TreeType::Leaf(TokenType::IntLit(val)) => Some(Value::Int(*val)),
+        TreeType::Leaf(TokenType::FloatLit(val)) => Some(Value::Float(*val)),
+        TreeType::Leaf(TokenType::BoolLit(val)) => Some(Value::Bool(*val)),
+        TreeType::Leaf(TokenType::StringLit(val)) => Some(Value::String(val.clone())),
+        TreeType::Leaf(TokenType::NoneLit) => Some(Value::None),
+        _ => None,
+    }
+}
+
+/// the literal for a value, None if it has none
+fn token(value: Value) -> Option<TokenType> {
+    match value {
+        Value::Int(val) => Some(TokenType::IntLit(val)),
+        Value::Float(val) => Some(TokenType::FloatLit(val)),
+        Value::Bool(val) => Some(TokenType::BoolLit(val)),
+        Value::String(val) => Some(TokenType::StringLit(val)),
+        Value::None => Some(TokenType::NoneLit),
+        _ => None,
+    }
+}
+
+fn is_function(item: &Tree) -> bool {
+    match item.value {
+        TreeType::Pub => is_function(&item.params[0]),
+        _ => item.value == TreeType::Function,
+    }
+}
+
+/// whether running a statement might call a function
+fn calls(tree: &Tree) -> bool {
+    tree.value == TreeType::Call || tree.params.iter().any(calls)
+}
+
+/// the name a top level statement declares, if it declares one
+fn declared(item: &Tree) -> Option<Symbol> {
+    match item.value {
+        TreeType::Pub => declared(&item.params[0]),
+        TreeType::Assign => Some(name(&item.params[1])),
+        _ => None,
+    }
+}
+
+fn name(leaf: &Tree) -> Symbol {
+    match &leaf.value {
+        TreeType::Leaf(TokenType::Identifier(name)) => *name,
+        other => panic!("({}) expected identifier, found {other:?}", leaf.line),
+    }
+}
@@ -1,89 +1,105 @@
 use crate::lexer::{Token, TokenType};
+use crate::symbol::Symbol;
 
 pub fn parse(tokens: Vec<Token>) -> Tree {
-    let mut iter = tokens.iter().peekable();
     let mut tree = Tree{
         line: 1,
+        end_line: 1,
+        start: 0,
+        end: 0,
         value: TreeType::File,
         params: Vec::new(),
     };
-    let mut line = 1;
-    while peek(&mut iter, &mut line).is_some() {
-        tree.params.push(get_tree(&mut iter, &mut line));
+    parse_items(&tokens, 0, |item, _| {
+        tree.params.push(item);
+        true
+    });
+    if let Some(last) = tokens.last() {
+        tree.end_line = last.line;
+        tree.end = last.end;
     }
     tree
 }
 
-fn get_tree(iter: &mut std::iter::Peekable<core::slice::Iter<Token>>, line: &mut usize) -> Tree {
-    let next_token = next(iter, line);
+/// parses top level trees one at a time, starting from the token at index pos
+/// f is given each tree along with the index of the token after it, parsing stops once it returns false
+pub fn parse_items(tokens: &[Token], pos: usize, mut f: impl FnMut(Tree, usize) -> bool) {
+    let mut iter = Tokens{
+        tokens,
+        pos,
+        depth: 0,
+        loops: Vec::new(),
+        deferring: false,
+        split_shift: false,
+    };
+    let mut line = 1;
+    while peek(&iter, &mut line).is_some() {
+        let item = get_tree(&mut iter, &mut line);
+        if !f(item, iter.pos) {
+            break;
+        }
+    }
+}
+
+fn get_tree(iter: &mut Tokens, line: &mut usize) -> Tree {
+    let next_token = peek(iter, line);
     if next_token.is_none() {
         panic!("({line}) expected token");
     }
+    // a label, which has to be followed by a loop
+    if let TokenType::Identifier(name) = next_token.unwrap().value
+        && iter.tokens.get(iter.pos + 1).is_some_and(|x| x.value.same_kind(&TokenType::Colon)) {
+        return get_label(name, iter, line);
+    }
+    // a lambda rather than a function declaration, since it has no name
+    if next_token.unwrap().value.same_kind(&TokenType::Function)
+        && iter.tokens.get(iter.pos + 1).is_some_and(|x| x.value.same_kind(&TokenType::LeftParen)) {
+        return get_statement(iter, line);
+    }
     match next_token.unwrap().value {
-        TokenType::LeftParen => todo!(),
-        TokenType::RightParen => todo!(),
-        TokenType::LeftBrack => todo!(),
-        TokenType::RightBrack => todo!(),
-        TokenType::LeftCurly => todo!(),
-        TokenType::RightCurly => todo!(),
-        TokenType::Comma => todo!(),
-        TokenType::Period => todo!(),
-        TokenType::Semicolon => todo!(),
-        TokenType::SQuote => todo!(),
-        TokenType::DQuote => todo!(),
-        TokenType::Backslash => todo!(),
-        TokenType::Arrow => todo!(),
-        TokenType::Assign => todo!(),
-        TokenType::NewLine => panic!("error in lexing"), // should be impoosible if iter handled correctly
-        TokenType::Plus => todo!(),
-        TokenType::Minus => todo!(),
-        TokenType::Star => todo!(),
-        TokenType::Slash => todo!(),
-        TokenType::Mod => todo!(),
-        TokenType::BwNot => todo!(),
-        TokenType::BwOr => todo!(),
-        TokenType::BwAnd => todo!(),
-        TokenType::BwXor => todo!(),
-        TokenType::Not => todo!(),
-        TokenType::Or => todo!(),
-        TokenType::And => todo!(),
-        TokenType::Xor => todo!(),
-        TokenType::Eq => todo!(),
-        TokenType::Lt => todo!(),
-        TokenType::Gt => todo!(),
-        TokenType::Neq => todo!(),
-        TokenType::Lte => todo!(),
-        TokenType::Gte => todo!(),
+        // tokens that can start an expression statement or a reassignment
+        TokenType::LeftParen | TokenType::Backslash | TokenType::Minus | TokenType::Not | TokenType::BwNot |
+        TokenType::IntLit(_) | TokenType::FloatLit(_) | TokenType::BoolLit(_) | TokenType::NoneLit |
+        TokenType::StringLit(_) | TokenType::Identifier(_) => return get_statement(iter, line),
+        _ => {},
+    }
+    match &next(iter, line).unwrap().value {
         TokenType::Const => get_assign(TokenType::Const, iter, line),
         TokenType::Var => get_assign(TokenType::Var, iter, line),
-        TokenType::Int => todo!(),
-        TokenType::Float => todo!(),
-        TokenType::Bool => todo!(),
-        TokenType::String => todo!(),
-        TokenType::Void => panic!("({line}) attempted to parse void token"), // will never happen, just here so rust doesnt get mad
-        TokenType::Enum => todo!(),
-        TokenType::Struct => todo!(),
         TokenType::Function => get_function(iter, line),
-        TokenType::If => todo!(),
-        TokenType::Else => todo!(),
-        TokenType::While => todo!(),
-        TokenType::Return => todo!(),
-        // V this is not true (e.g. punctuation) V, change to check for that, then make all else panic
-        // only literals or identifiers
-        _ => Tree::token_to_leaf(next_token.unwrap())
+        TokenType::If => get_if(iter, line),
+        TokenType::Switch => get_switch(iter, line),
+        TokenType::Else => panic!("({line}) 'else' without matching 'if'"),
+        TokenType::While => get_while(None, iter, line),
+        TokenType::For => get_for(None, iter, line),
+        TokenType::Forever => get_forever(None, iter, line),
+        TokenType::Return => get_return(iter, line),
+        TokenType::Defer => get_defer(iter, line),
+        TokenType::Try => get_try(iter, line),
+        TokenType::Catch => panic!("({line}) 'catch' without matching 'try'"),
+        TokenType::Throw => get_throw(iter, line),
+        token @ (TokenType::Break | TokenType::Continue) => get_loop_control(token, iter, line),
+        TokenType::Import => get_import(iter, line),
+        TokenType::Pub => get_pub(iter, line),
+        TokenType::Enum => get_enum(iter, line),
+        TokenType::Struct => get_struct(iter, line),
+        token => panic!("({line}) unexpected '{token}'"),
     }
 }
 
-fn get_assign(variable_type: TokenType, iter: &mut std::iter::Peekable<core::slice::Iter<Token>>, line: &mut usize) -> Tree {
+fn get_assign(variable_type: TokenType, iter: &mut Tokens, line: &mut usize) -> Tree {
     // init assign tree
     let mut new_asn = Tree{
         line: *line,
+        end_line: *line,
+        start: iter.prev_start(),
+        end: iter.prev_start(),
         value: TreeType::Assign,
         params: Vec::with_capacity(4)
     };
 
     // var or const
-    new_asn.params.push(Tree::leaf(variable_type, *line));
+    new_asn.params.push(Tree::leaf(variable_type, *line, iter.prev_start(), iter.prev_end()));
 
     // variable name
     let mut next_token = next(iter, line);
@@ -92,27 +108,25 @@ fn get_assign(variable_type: TokenType, iter: &mut std::iter::Peekable<core::sli
     }
     match next_token.unwrap().value {
         TokenType::Identifier(_) => {
-            new_asn.params.push(Tree::leaf(next_token.unwrap().value.clone(), *line));
+            new_asn.params.push(Tree::token_to_leaf(next_token.unwrap()));
         },
         _ => panic!("({line}) expected variable name in assignment"),
     }
 
-    // check for ':'
-    if next(iter, line).is_none_or(|x| x.value != TokenType::Colon) {
-        panic!("({line}) expected ':' in assignment");
+    // variable type, which is inferred from the value if there is no ':'
+    if peek(iter, line).is_some_and(|x| x.value.same_kind(&TokenType::Colon)) {
+        next(iter, line);
+        new_asn.params.push(get_type("variable type in assignment", iter, line));
     }
-
-    // variable type
-    next_token = next(iter, line);
-    if next_token.is_none() {
-        panic!("({line}) expected variable type in assignment");
-    }
-    match next_token.unwrap().value {
-        TokenType::Int | TokenType::Float | TokenType::Bool | TokenType::String |
-        TokenType::Identifier(_) => {
-            new_asn.params.push(Tree::leaf(next_token.unwrap().value.clone(), *line));
-        },
-        _ => panic!("({line}) expected variable type in assignment")
+    else {
+        new_asn.params.push(Tree{
+            line: *line,
+            end_line: *line,
+            start: iter.prev_end(),
+            end: iter.prev_end(),
+            value: TreeType::Inferred,
+            params: Vec::new(),
+        });
     }
 
     // check for '=' or ';'
@@ -124,41 +138,947 @@ fn get_assign(variable_type: TokenType, iter: &mut std::iter::Peekable<core::sli
         TokenType::Semicolon => {}, // do nothing, this is just a declare
         TokenType::Assign => {
             new_asn.params.push(get_expression(iter, line));
+            if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::Semicolon)) {
+                panic!("({line}) expected ';' after assignment");
+            }
         },
         _ => panic!("({line}) expected '=' or ';' in assignment")
     }
 
+    new_asn.end_line = *line;
+    new_asn.end = iter.prev_end();
     new_asn
 }
 
-fn get_expression(iter: &mut std::iter::Peekable<core::slice::Iter<Token>>, line: &mut usize) -> Tree {
-    let mut new_exp = Tree{
-        line: *line,
+/// parses a statement that starts with an expression, either a reassignment (`x = ...;`) or a
+/// bare expression such as a function call
+fn get_statement(iter: &mut Tokens, line: &mut usize) -> Tree {
+    let exp = get_expression(iter, line);
+    let next_token = next(iter, line);
+    if next_token.is_none() {
+        panic!("({line}) expected ';' after expression");
+    }
+    match next_token.unwrap().value {
+        TokenType::Semicolon => {
+            let mut exp = exp;
+            exp.end_line = *line;
+            exp.end = iter.prev_end();
+            exp
+        },
+        TokenType::Assign => {
+            // only plain variables can be reassigned
+            let target = exp.params.into_iter().next().unwrap();
+            if !matches!(target.value, TreeType::Leaf(TokenType::Identifier(_))) {
+                panic!("({line}) invalid left hand side of assignment");
+            }
+            let mut new_rsn = Tree{
+                line: target.line,
+                end_line: *line,
+                start: target.start,
+                end: target.end,
+                value: TreeType::Reassign,
+                params: vec![target, get_expression(iter, line)],
+            };
+            if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::Semicolon)) {
+                panic!("({line}) expected ';' after assignment");
+            }
+            new_rsn.end_line = *line;
+            new_rsn.end = iter.prev_end();
+            new_rsn
+        },
+        _ => panic!("({line}) expected ';' after expression"),
+    }
+}
+
+/// parses a single expression, leaving whatever follows it (e.g. ';' or '{') in the iter
+fn get_expression(iter: &mut Tokens, line: &mut usize) -> Tree {
+    let start = iter.next_start();
+    let root = get_binary(0, iter, line);
+    Tree{
+        line: root.line,
+        end_line: root.end_line,
+        start,
+        end: iter.prev_end(),
         value: TreeType::Expression,
-        params: Vec::new()
+        params: vec![root],
+    }
+}
+
+/// precedence climbing over the binary operators, only operators that bind at least as tightly
+/// as min_prec are consumed
+fn get_binary(min_prec: u8, iter: &mut Tokens, line: &mut usize) -> Tree {
+    let mut lhs = get_unary(iter, line);
+    while let Some(next_token) = peek(iter, line) {
+        let op = next_token.value.clone();
+        let prec = match precedence(&op) {
+            Some(prec) if prec >= min_prec => prec,
+            _ => break,
+        };
+        next(iter, line);
+        let rhs = get_binary(prec + 1, iter, line); // + 1 makes every operator left associative
+        lhs = Tree{
+            line: lhs.line,
+            end_line: rhs.end_line,
+            start: lhs.start,
+            end: rhs.end,
+            value: TreeType::BinaryOp(op),
+            params: vec![lhs, rhs],
+        };
+    }
+    lhs
+}
+
+fn get_unary(iter: &mut Tokens, line: &mut usize) -> Tree {
+    let next_token = peek(iter, line);
+    if next_token.is_none() {
+        panic!("({line}) expected expression");
+    }
+    iter.enter(*line);
+    let exp = match next_token.unwrap().value {
+        TokenType::Minus | TokenType::Not | TokenType::BwNot => {
+            let op = next(iter, line).unwrap().value.clone();
+            let op_line = *line;
+            let start = iter.prev_start();
+            let operand = get_unary(iter, line);
+            Tree{
+                line: op_line,
+                end_line: operand.end_line,
+                start,
+                end: operand.end,
+                value: TreeType::UnaryOp(op),
+                params: vec![operand],
+            }
+        },
+        _ => get_postfix(iter, line),
     };
+    iter.leave();
+    exp
+}
 
-    // collect all of the tokens to be parsed
-    let mut tokens: Vec<Token> = Vec::new();
+/// parses a primary expression followed by any number of calls and member accesses, e.g. `utils.f(x)(y)`
+fn get_postfix(iter: &mut Tokens, line: &mut usize) -> Tree {
+    let mut exp = get_primary(iter, line);
     loop {
+        match peek(iter, line).map(|x| &x.value) {
+            Some(TokenType::LeftParen) => {},
+            Some(TokenType::Period) => {
+                next(iter, line);
+                let name = match next(iter, line) {
+                    Some(token @ Token{ value: TokenType::Identifier(_), .. }) => Tree::token_to_leaf(token),
+                    _ => panic!("({line}) expected name after '.'"),
+                };
+                exp = Tree{
+                    line: exp.line,
+                    end_line: name.end_line,
+                    start: exp.start,
+                    end: name.end,
+                    value: TreeType::Member,
+                    params: vec![exp, name],
+                };
+                continue;
+            },
+            _ => break,
+        }
+        next(iter, line);
+        let mut args = Tree{
+            line: *line,
+            end_line: *line,
+            start: iter.prev_start(),
+            end: iter.prev_end(),
+            value: TreeType::Arguments,
+            params: Vec::new(),
+        };
+        if peek(iter, line).is_some_and(|x| x.value.same_kind(&TokenType::RightParen)) {
+            next(iter, line);
+        }
+        else {
+            loop {
+                args.params.push(get_binary(0, iter, line));
+                let next_token = next(iter, line);
+                match next_token.map(|x| &x.value) {
+                    Some(TokenType::Comma) => continue,
+                    Some(TokenType::RightParen) => break,
+                    _ => panic!("({line}) expected ',' or ')' in function call"),
+                }
+            }
+        }
+        args.end_line = *line;
+        args.end = iter.prev_end();
+        exp = Tree{
+            line: exp.line,
+            end_line: *line,
+            start: exp.start,
+            end: args.end,
+            value: TreeType::Call,
+            params: vec![exp, args],
+        };
+    }
+    exp
+}
+
+fn get_primary(iter: &mut Tokens, line: &mut usize) -> Tree {
+    let next_token = next(iter, line);
+    if next_token.is_none() {
+        panic!("({line}) expected expression");
+    }
+    match next_token.unwrap().value {
+        TokenType::IntLit(_) | TokenType::FloatLit(_) | TokenType::BoolLit(_) | TokenType::NoneLit |
+        TokenType::StringLit(_) | TokenType::Identifier(_) => Tree::token_to_leaf(next_token.unwrap()),
+        TokenType::LeftParen => {
+            let start = iter.prev_start();
+            let mut exp = get_binary(0, iter, line);
+            if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::RightParen)) {
+                panic!("({line}) expected ')'");
+            }
+            // the parentheses are part of the span even though they have no tree of their own
+            exp.start = start;
+            exp.end = iter.prev_end();
+            exp
+        },
+        TokenType::Backslash | TokenType::Function => get_lambda(iter, line),
+        TokenType::LeftBrack => {
+            let mut array = Tree{
+                line: *line,
+                end_line: *line,
+                start: iter.prev_start(),
+                end: iter.prev_end(),
+                value: TreeType::Array,
+                params: Vec::new(),
+            };
+            // elements, which can be followed by a trailing comma
+            while peek(iter, line).is_some_and(|x| !x.value.same_kind(&TokenType::RightBrack)) {
+                array.params.push(get_binary(0, iter, line));
+                match peek(iter, line).map(|x| &x.value) {
+                    Some(TokenType::Comma) => { next(iter, line); },
+                    Some(TokenType::RightBrack) => {},
+                    _ => panic!("({line}) expected ',' or ']' in array"),
+                }
+            }
+            if next(iter, line).is_none() {
+                panic!("({line}) expected ']'");
+            }
+            array.end_line = *line;
+            array.end = iter.prev_end();
+            array
+        },
+        ref token => panic!("({line}) expected expression, found '{token}'"),
+    }
+}
+
+/// binding strength of a binary operator, higher binds tighter
+/// returns None if the token is not a binary operator
+pub fn precedence(op: &TokenType) -> Option<u8> {
+    match op {
+        TokenType::Coalesce => Some(0),
+        TokenType::Or => Some(1),
+        TokenType::Xor => Some(2),
+        TokenType::And => Some(3),
+        TokenType::BwOr => Some(4),
+        TokenType::BwXor => Some(5),
+        TokenType::BwAnd => Some(6),
+        TokenType::Eq | TokenType::Neq => Some(7),
+        TokenType::Lt | TokenType::Gt | TokenType::Lte | TokenType::Gte => Some(8),
+        TokenType::ShiftLeft | TokenType::ShiftRight => Some(9),
+        TokenType::Plus | TokenType::Minus => Some(10),
+        TokenType::Star | TokenType::Slash | TokenType::Mod => Some(11),
+        _ => None,
+    }
+}
+
+fn get_if(iter: &mut Tokens, line: &mut usize) -> Tree {
+    let mut new_if = Tree{
+        line: *line,
+        end_line: *line,
+        start: iter.prev_start(),
+        end: iter.prev_end(),
+        value: TreeType::If,
+        params: Vec::with_capacity(3),
+    };
+
+    // condition, or a `let` that unwraps an optional
+    if peek(iter, line).is_some_and(|x| x.value.same_kind(&TokenType::Let)) {
+        next(iter, line);
+        new_if.params.push(get_let(iter, line));
+    }
+    else {
+        new_if.params.push(get_expression(iter, line));
+    }
+
+    // body
+    if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::LeftCurly)) {
+        panic!("({line}) expected '{{' after if condition");
+    }
+    new_if.params.push(get_body(iter, line));
+
+    // else, which is either another if or a body
+    if peek(iter, line).is_some_and(|x| x.value.same_kind(&TokenType::Else)) {
+        next(iter, line);
         let next_token = next(iter, line);
-        if next_token.is_none() {
-            panic!("({line}) expected expression or ';'");
+        match next_token.map(|x| &x.value) {
+            Some(TokenType::If) => {
+                iter.enter(*line);
+                new_if.params.push(get_if(iter, line));
+                iter.leave();
+            },
+            Some(TokenType::LeftCurly) => new_if.params.push(get_body(iter, line)),
+            _ => panic!("({line}) expected 'if' or '{{' after 'else'"),
         }
-        match next_token.unwrap().value {
-            TokenType::Semicolon => break,
-            _ => tokens.push(next_token.unwrap().clone())
+    }
+
+    new_if.end_line = new_if.params.last().unwrap().end_line;
+    new_if.end = iter.prev_end();
+    new_if
+}
+
+/// parses the `name = value` of an `if let`, after the 'let'
+fn get_let(iter: &mut Tokens, line: &mut usize) -> Tree {
+    let mut new_let = Tree{
+        line: *line,
+        end_line: *line,
+        start: iter.prev_start(),
+        end: iter.prev_end(),
+        value: TreeType::Let,
+        params: Vec::with_capacity(2),
+    };
+
+    // name, then '='
+    match next(iter, line) {
+        Some(token @ Token{ value: TokenType::Identifier(_), .. }) => new_let.params.push(Tree::token_to_leaf(token)),
+        _ => panic!("({line}) expected variable name after 'let'"),
+    }
+    if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::Assign)) {
+        panic!("({line}) expected '=' after variable name in 'let'");
+    }
+
+    // the optional being unwrapped
+    new_let.params.push(get_expression(iter, line));
+
+    new_let.end_line = *line;
+    new_let.end = iter.prev_end();
+    new_let
+}
+
+/// parses a loop after its label, giving it the label's name
+fn get_label(name: Symbol, iter: &mut Tokens, line: &mut usize) -> Tree {
+    let mut new_label = Tree{
+        line: *line,
+        end_line: *line,
+        start: iter.next_start(),
+        end: iter.next_start(),
+        value: TreeType::Label,
+        params: Vec::with_capacity(2),
+    };
+
+    // name, then ':'
+    new_label.params.push(Tree::token_to_leaf(next(iter, line).unwrap()));
+    next(iter, line);
+
+    // the loop being labeled
+    match next(iter, line).map(|x| &x.value) {
+        Some(TokenType::While) => new_label.params.push(get_while(Some(name), iter, line)),
+        Some(TokenType::For) => new_label.params.push(get_for(Some(name), iter, line)),
+        Some(TokenType::Forever) => new_label.params.push(get_forever(Some(name), iter, line)),
+        _ => panic!("({line}) expected loop after label '{name}'"),
+    }
+
+    new_label.end_line = *line;
+    new_label.end = iter.prev_end();
+    new_label
+}
+
+fn get_switch(iter: &mut Tokens, line: &mut usize) -> Tree {
+    let mut new_switch = Tree{
+        line: *line,
+        end_line: *line,
+        start: iter.prev_start(),
+        end: iter.prev_end(),
+        value: TreeType::Switch,
+        params: Vec::new(),
+    };
+
+    // the value being matched
+    new_switch.params.push(get_expression(iter, line));
+    if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::LeftCurly)) {
+        panic!("({line}) expected '{{' after switch value");
+    }
+
+    // arms, each of which is one or more patterns, an optional guard and a body
+    while peek(iter, line).is_some_and(|x| !x.value.same_kind(&TokenType::RightCurly)) {
+        let mut patterns = Tree{
+            line: *line,
+            end_line: *line,
+            start: iter.next_start(),
+            end: iter.next_start(),
+            value: TreeType::Patterns,
+            params: Vec::new(),
+        };
+        loop {
+            patterns.params.push(get_pattern(iter, line));
+            match peek(iter, line).map(|x| &x.value) {
+                Some(TokenType::Comma) => { next(iter, line); },
+                _ => break,
+            }
         }
+        patterns.end_line = *line;
+        patterns.end = iter.prev_end();
+
+        let guard = match peek(iter, line).map(|x| &x.value) {
+            Some(TokenType::If) => {
+                next(iter, line);
+                Some(get_expression(iter, line))
+            },
+            _ => None,
+        };
+        if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::LeftCurly)) {
+            panic!("({line}) expected '{{' after switch pattern");
+        }
+        let body = get_body(iter, line);
+
+        let mut arm = Tree{
+            line: patterns.line,
+            end_line: body.end_line,
+            start: patterns.start,
+            end: body.end,
+            value: TreeType::Arm,
+            params: vec![patterns, body],
+        };
+        arm.params.extend(guard);
+        new_switch.params.push(arm);
+    }
+    if next(iter, line).is_none() {
+        panic!("({line}) expected '}}'");
     }
-    // next(iter, line); // remove ';' from the iter // copied (wrong?) from get_body
 
-    new_exp
+    new_switch.end_line = *line;
+    new_switch.end = iter.prev_end();
+    new_switch
 }
 
-fn get_function(iter: &mut std::iter::Peekable<core::slice::Iter<Token>>, line: &mut usize) -> Tree {
+/// parses one pattern of a switch arm: a literal, a range of literals, a variant of an enum
+/// with patterns for its fields, or a name that matches anything and binds it (`_` to not bind it)
+fn get_pattern(iter: &mut Tokens, line: &mut usize) -> Tree {
+    iter.enter(*line);
+    let next_token = next(iter, line);
+    let pattern = match next_token.map(|x| &x.value) {
+        Some(TokenType::Identifier(_)) if peek(iter, line).is_some_and(|x| x.value.same_kind(&TokenType::Period)) => {
+            let token = next_token.unwrap();
+            let mut variant = Tree{
+                line: token.line,
+                end_line: token.line,
+                start: token.start,
+                end: token.end,
+                value: TreeType::VariantPattern,
+                params: vec![Tree::token_to_leaf(token)],
+            };
+            next(iter, line);
+            match next(iter, line) {
+                Some(token @ Token{ value: TokenType::Identifier(_), .. }) => variant.params.push(Tree::token_to_leaf(token)),
+                _ => panic!("({line}) expected variant name after '.'"),
+            }
+            // field patterns
+            if peek(iter, line).is_some_and(|x| x.value.same_kind(&TokenType::LeftParen)) {
+                next(iter, line);
+                loop {
+                    variant.params.push(get_pattern(iter, line));
+                    match next(iter, line).map(|x| &x.value) {
+                        Some(TokenType::Comma) => continue,
+                        Some(TokenType::RightParen) => break,
+                        _ => panic!("({line}) expected ',' or ')' in variant pattern"),
+                    }
+                }
+            }
+            variant.end_line = *line;
+            variant.end = iter.prev_end();
+            variant
+        },
+        Some(TokenType::Identifier(_)) => Tree::token_to_leaf(next_token.unwrap()),
+        Some(_) => {
+            let low = get_literal_pattern(next_token.unwrap(), iter, line);
+            match peek(iter, line).map(|x| &x.value) {
+                Some(op @ (TokenType::Range | TokenType::RangeInclusive)) => {
+                    next(iter, line);
+                    let high = match next(iter, line) {
+                        Some(token) => get_literal_pattern(token, iter, line),
+                        None => panic!("({line}) expected end of range"),
+                    };
+                    Tree{
+                        line: low.line,
+                        end_line: high.end_line,
+                        start: low.start,
+                        end: high.end,
+                        value: TreeType::Range(op.clone()),
+                        params: vec![low, high],
+                    }
+                },
+                _ => low,
+            }
+        },
+        None => panic!("({line}) expected pattern"),
+    };
+    iter.leave();
+    pattern
+}
+
+/// a literal leaf in a pattern starting at token, which can be a negative number
+fn get_literal_pattern(token: &Token, iter: &mut Tokens, line: &mut usize) -> Tree {
+    match &token.value {
+        TokenType::IntLit(_) | TokenType::FloatLit(_) | TokenType::BoolLit(_) |
+        TokenType::StringLit(_) => Tree::token_to_leaf(token),
+        TokenType::Minus => {
+            let value = match next(iter, line).map(|x| &x.value) {
+                Some(TokenType::IntLit(int)) => TokenType::IntLit(-int),
+                Some(TokenType::FloatLit(float)) => TokenType::FloatLit(-float),
+                _ => panic!("({line}) expected number after '-' in pattern"),
+            };
+            Tree::leaf(value, *line, token.start, iter.prev_end())
+        },
+        other => panic!("({line}) expected pattern, found '{other}'"),
+    }
+}
+
+/// label is the name of the loop if it has one, for break and continue inside it to refer to
+fn get_while(label: Option<Symbol>, iter: &mut Tokens, line: &mut usize) -> Tree {
+    let mut new_while = Tree{
+        line: *line,
+        end_line: *line,
+        start: iter.prev_start(),
+        end: iter.prev_end(),
+        value: TreeType::While,
+        params: Vec::with_capacity(2),
+    };
+
+    // condition
+    new_while.params.push(get_expression(iter, line));
+
+    // body
+    if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::LeftCurly)) {
+        panic!("({line}) expected '{{' after while condition");
+    }
+    iter.loops.push(label);
+    new_while.params.push(get_body(iter, line));
+    iter.loops.pop();
+
+    new_while.end_line = *line;
+    new_while.end = iter.prev_end();
+    new_while
+}
+
+fn get_for(label: Option<Symbol>, iter: &mut Tokens, line: &mut usize) -> Tree {
+    let mut new_for = Tree{
+        line: *line,
+        end_line: *line,
+        start: iter.prev_start(),
+        end: iter.prev_end(),
+        value: TreeType::For,
+        params: Vec::with_capacity(3),
+    };
+
+    // loop variable
+    match next(iter, line) {
+        Some(token @ Token{ value: TokenType::Identifier(_), .. }) => new_for.params.push(Tree::token_to_leaf(token)),
+        _ => panic!("({line}) expected loop variable after 'for'"),
+    }
+    if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::In)) {
+        panic!("({line}) expected 'in' after loop variable");
+    }
+
+    // what is iterated over, either a range or an expression
+    let start = get_expression(iter, line);
+    match peek(iter, line).map(|x| &x.value) {
+        Some(op @ (TokenType::Range | TokenType::RangeInclusive)) => {
+            next(iter, line);
+            let end = get_expression(iter, line);
+            new_for.params.push(Tree{
+                line: start.line,
+                end_line: end.end_line,
+                start: start.start,
+                end: end.end,
+                value: TreeType::Range(op.clone()),
+                params: vec![start, end],
+            });
+        },
+        _ => new_for.params.push(start),
+    }
+
+    // body
+    if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::LeftCurly)) {
+        panic!("({line}) expected '{{' after for loop");
+    }
+    iter.loops.push(label);
+    new_for.params.push(get_body(iter, line));
+    iter.loops.pop();
+
+    new_for.end_line = *line;
+    new_for.end = iter.prev_end();
+    new_for
+}
+
+/// a loop without a condition, which only stops when something breaks out of it
+fn get_forever(label: Option<Symbol>, iter: &mut Tokens, line: &mut usize) -> Tree {
+    let mut new_forever = Tree{
+        line: *line,
+        end_line: *line,
+        start: iter.prev_start(),
+        end: iter.prev_end(),
+        value: TreeType::Forever,
+        params: Vec::with_capacity(1),
+    };
+
+    // body
+    if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::LeftCurly)) {
+        panic!("({line}) expected '{{' after 'forever'");
+    }
+    iter.loops.push(label);
+    new_forever.params.push(get_body(iter, line));
+    iter.loops.pop();
+
+    new_forever.end_line = *line;
+    new_forever.end = iter.prev_end();
+    new_forever
+}
+
+/// parses break or continue, which has to be inside a loop with the label it names if it names one
+fn get_loop_control(token: &TokenType, iter: &mut Tokens, line: &mut usize) -> Tree {
+    let mut new_control = Tree{
+        line: *line,
+        end_line: *line,
+        start: iter.prev_start(),
+        end: iter.prev_end(),
+        value: match token {
+            TokenType::Break => TreeType::Break,
+            _ => TreeType::Continue,
+        },
+        params: Vec::with_capacity(1),
+    };
+
+    // label, if any
+    let label = match peek(iter, line) {
+        Some(Token{value: TokenType::Identifier(name), ..}) => {
+            new_control.params.push(Tree::token_to_leaf(next(iter, line).unwrap()));
+            Some(*name)
+        },
+        _ => None,
+    };
+    match label {
+        None if iter.loops.is_empty() => panic!("({line}) '{token}' outside of a loop"),
+        Some(label) if !iter.loops.contains(&Some(label)) => panic!("({line}) no loop labeled '{label}' around '{token}'"),
+        _ => {},
+    }
+    if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::Semicolon)) {
+        panic!("({line}) expected ';' after '{token}'");
+    }
+
+    new_control.end_line = *line;
+    new_control.end = iter.prev_end();
+    new_control
+}
+
+/// parses a statement to run when the enclosing body is left, which cannot itself leave it
+fn get_defer(iter: &mut Tokens, line: &mut usize) -> Tree {
+    let start = iter.prev_start();
+    let defer_line = *line;
+    if iter.depth == 0 {
+        panic!("({line}) 'defer' is only allowed inside a body");
+    }
+    // loops around the defer cannot be broken out of from inside it
+    let loops = std::mem::take(&mut iter.loops);
+    let was_deferring = std::mem::replace(&mut iter.deferring, true);
+    let statement = match peek(iter, line).map(|x| &x.value) {
+        Some(token @ (TokenType::Const | TokenType::Var | TokenType::Defer)) => panic!("({line}) '{token}' cannot be deferred"),
+        Some(_) => get_tree(iter, line),
+        None => panic!("({line}) expected statement after 'defer'"),
+    };
+    iter.deferring = was_deferring;
+    iter.loops = loops;
+    Tree{
+        line: defer_line,
+        end_line: statement.end_line,
+        start,
+        end: statement.end,
+        value: TreeType::Defer,
+        params: vec![statement],
+    }
+}
+
+fn get_return(iter: &mut Tokens, line: &mut usize) -> Tree {
+    if iter.deferring {
+        panic!("({line}) cannot return from a deferred statement");
+    }
+    let mut new_ret = Tree{
+        line: *line,
+        end_line: *line,
+        start: iter.prev_start(),
+        end: iter.prev_end(),
+        value: TreeType::Return,
+        params: Vec::with_capacity(1),
+    };
+
+    // return value, if any
+    if peek(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::Semicolon)) {
+        new_ret.params.push(get_expression(iter, line));
+    }
+    if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::Semicolon)) {
+        panic!("({line}) expected ';' after return");
+    }
+
+    new_ret.end_line = *line;
+    new_ret.end = iter.prev_end();
+    new_ret
+}
+
+/// `try { ... } catch name { ... }`
+fn get_try(iter: &mut Tokens, line: &mut usize) -> Tree {
+    let mut new_try = Tree{
+        line: *line,
+        end_line: *line,
+        start: iter.prev_start(),
+        end: iter.prev_end(),
+        value: TreeType::Try,
+        params: Vec::with_capacity(3),
+    };
+
+    // body
+    if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::LeftCurly)) {
+        panic!("({line}) expected '{{' after 'try'");
+    }
+    new_try.params.push(get_body(iter, line));
+
+    // catch, with the name the error is bound to
+    if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::Catch)) {
+        panic!("({line}) expected 'catch' after try body");
+    }
+    match next(iter, line) {
+        Some(token @ Token{ value: TokenType::Identifier(_), .. }) => new_try.params.push(Tree::token_to_leaf(token)),
+        _ => panic!("({line}) expected variable name after 'catch'"),
+    }
+    if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::LeftCurly)) {
+        panic!("({line}) expected '{{' after catch variable");
+    }
+    new_try.params.push(get_body(iter, line));
+
+    new_try.end_line = *line;
+    new_try.end = iter.prev_end();
+    new_try
+}
+
+fn get_throw(iter: &mut Tokens, line: &mut usize) -> Tree {
+    let mut new_throw = Tree{
+        line: *line,
+        end_line: *line,
+        start: iter.prev_start(),
+        end: iter.prev_end(),
+        value: TreeType::Throw,
+        params: Vec::with_capacity(1),
+    };
+
+    new_throw.params.push(get_expression(iter, line));
+    if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::Semicolon)) {
+        panic!("({line}) expected ';' after throw");
+    }
+
+    new_throw.end_line = *line;
+    new_throw.end = iter.prev_end();
+    new_throw
+}
+
+/// `import name;` or `import "path";`
+fn get_import(iter: &mut Tokens, line: &mut usize) -> Tree {
+    let start = iter.prev_start();
+    let import_line = *line;
+    let module = match next(iter, line) {
+        Some(token @ Token{ value: TokenType::Identifier(_) | TokenType::StringLit(_), .. }) => Tree::token_to_leaf(token),
+        _ => panic!("({line}) expected module name or path after 'import'"),
+    };
+    if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::Semicolon)) {
+        panic!("({line}) expected ';' after import");
+    }
+    Tree{
+        line: import_line,
+        end_line: *line,
+        start,
+        end: iter.prev_end(),
+        value: TreeType::Import,
+        params: vec![module],
+    }
+}
+
+/// `pub` followed by the function or variable that it exports
+fn get_pub(iter: &mut Tokens, line: &mut usize) -> Tree {
+    let start = iter.prev_start();
+    let pub_line = *line;
+    let item = match next(iter, line).map(|x| &x.value) {
+        Some(TokenType::Function) => get_function(iter, line),
+        Some(TokenType::Const) => get_assign(TokenType::Const, iter, line),
+        Some(TokenType::Var) => get_assign(TokenType::Var, iter, line),
+        Some(TokenType::Enum) => get_enum(iter, line),
+        Some(TokenType::Struct) => get_struct(iter, line),
+        _ => panic!("({line}) expected function, variable, enum or struct after 'pub'"),
+    };
+    Tree{
+        line: pub_line,
+        end_line: item.end_line,
+        start,
+        end: item.end,
+        value: TreeType::Pub,
+        params: vec![item],
+    }
+}
+
+/// parses an enum declaration, e.g. `enum Shape { Circle(float), Square(float, float), Empty }`
+fn get_enum(iter: &mut Tokens, line: &mut usize) -> Tree {
+    let mut new_enum = Tree{
+        line: *line,
+        end_line: *line,
+        start: iter.prev_start(),
+        end: iter.prev_end(),
+        value: TreeType::Enum,
+        params: Vec::new(),
+    };
+
+    // enum name
+    match next(iter, line) {
+        Some(token @ Token{ value: TokenType::Identifier(_), .. }) => new_enum.params.push(Tree::token_to_leaf(token)),
+        _ => panic!("({line}) expected enum name"),
+    }
+    if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::LeftCurly)) {
+        panic!("({line}) expected '{{' after enum name");
+    }
+
+    // variants, which can be followed by a trailing comma
+    loop {
+        let mut variant = match next(iter, line) {
+            Some(Token{ value: TokenType::RightCurly, .. }) => break,
+            Some(token @ Token{ value: TokenType::Identifier(_), .. }) => Tree{
+                line: token.line,
+                end_line: token.line,
+                start: token.start,
+                end: token.end,
+                value: TreeType::Variant,
+                params: vec![Tree::token_to_leaf(token)],
+            },
+            _ => panic!("({line}) expected variant name or '}}' in enum"),
+        };
+
+        // field types
+        if peek(iter, line).is_some_and(|x| x.value.same_kind(&TokenType::LeftParen)) {
+            next(iter, line);
+            loop {
+                variant.params.push(get_type("field type in enum variant", iter, line));
+                match next(iter, line).map(|x| &x.value) {
+                    Some(TokenType::Comma) => continue,
+                    Some(TokenType::RightParen) => break,
+                    _ => panic!("({line}) expected ',' or ')' in enum variant"),
+                }
+            }
+            variant.end_line = *line;
+            variant.end = iter.prev_end();
+        }
+        new_enum.params.push(variant);
+
+        match next(iter, line).map(|x| &x.value) {
+            Some(TokenType::Comma) => continue,
+            Some(TokenType::RightCurly) => break,
+            _ => panic!("({line}) expected ',' or '}}' in enum"),
+        }
+    }
+
+    new_enum.end_line = *line;
+    new_enum.end = iter.prev_end();
+    new_enum
+}
+
+/// parses a struct declaration, e.g. `struct Pair<A, B> { first: A, second: B }`
+fn get_struct(iter: &mut Tokens, line: &mut usize) -> Tree {
+    let mut new_struct = Tree{
+        line: *line,
+        end_line: *line,
+        start: iter.prev_start(),
+        end: iter.prev_end(),
+        value: TreeType::Struct,
+        params: Vec::new(),
+    };
+
+    // struct name
+    match next(iter, line) {
+        Some(token @ Token{ value: TokenType::Identifier(_), .. }) => new_struct.params.push(Tree::token_to_leaf(token)),
+        _ => panic!("({line}) expected struct name"),
+    }
+    new_struct.params.push(get_type_parameters(iter, line));
+    if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::LeftCurly)) {
+        panic!("({line}) expected '{{' after struct name");
+    }
+
+    // fields, which can be followed by a trailing comma
+    loop {
+        let mut field = match next(iter, line) {
+            Some(Token{ value: TokenType::RightCurly, .. }) => break,
+            Some(token @ Token{ value: TokenType::Identifier(_), .. }) => Tree{
+                line: token.line,
+                end_line: token.line,
+                start: token.start,
+                end: token.end,
+                value: TreeType::Field,
+                params: vec![Tree::token_to_leaf(token)],
+            },
+            _ => panic!("({line}) expected field name or '}}' in struct"),
+        };
+        if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::Colon)) {
+            panic!("({line}) expected ':' after field name");
+        }
+        field.params.push(get_type("field type in struct", iter, line));
+        field.end_line = *line;
+        field.end = iter.prev_end();
+        new_struct.params.push(field);
+
+        match next(iter, line).map(|x| &x.value) {
+            Some(TokenType::Comma) => continue,
+            Some(TokenType::RightCurly) => break,
+            _ => panic!("({line}) expected ',' or '}}' in struct"),
+        }
+    }
+
+    new_struct.end_line = *line;
+    new_struct.end = iter.prev_end();
+    new_struct
+}
+
+/// parses the type parameters of a declaration, e.g. `<A, B>`, which are empty without a '<'
+fn get_type_parameters(iter: &mut Tokens, line: &mut usize) -> Tree {
+    let mut type_params = Tree{
+        line: *line,
+        end_line: *line,
+        start: iter.next_start(),
+        end: iter.next_start(),
+        value: TreeType::TypeParameters,
+        params: Vec::new(),
+    };
+    if peek(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::Lt)) {
+        return type_params;
+    }
+    next(iter, line);
+    loop {
+        match next(iter, line) {
+            Some(token @ Token{ value: TokenType::Identifier(_), .. }) => type_params.params.push(Tree::token_to_leaf(token)),
+            _ => panic!("({line}) expected type parameter name"),
+        }
+        match next(iter, line).map(|x| &x.value) {
+            Some(TokenType::Comma) => continue,
+            Some(TokenType::Gt) => break,
+            _ => panic!("({line}) expected ',' or '>' after type parameter"),
+        }
+    }
+    type_params.end_line = *line;
+    type_params.end = iter.prev_end();
+    type_params
+}
+
+fn get_function(iter: &mut Tokens, line: &mut usize) -> Tree {
     // init fn tree
     let mut new_fn = Tree{
         line: *line,
+        end_line: *line,
+        start: iter.prev_start(),
+        end: iter.prev_end(),
         value: TreeType::Function,
         params: Vec::with_capacity(4)
     };
@@ -167,12 +1087,41 @@ fn get_function(iter: &mut std::iter::Peekable<core::slice::Iter<Token>>, line:
     let fn_name = next(iter, line).unwrap_or_else(||
         panic!("({line}) expected function name")).value.clone();
     match fn_name {
-        TokenType::Identifier(_) => new_fn.params.push(Tree::leaf(fn_name, *line)),
+        TokenType::Identifier(_) => new_fn.params.push(Tree::leaf(fn_name, *line, iter.prev_start(), iter.prev_end())),
         token => panic!("({line}) {token} is not a valid function name"),
     }
+    let type_params = get_type_parameters(iter, line);
+
+    get_signature_and_body(&mut new_fn, iter, line);
+    // the type parameters go last, so that generic and other functions share their layout otherwise
+    if !type_params.params.is_empty() {
+        new_fn.params.push(type_params);
+    }
+    new_fn.end_line = *line;
+    new_fn.end = iter.prev_end();
+    new_fn
+}
+
+/// parses an anonymous function expression after its '\' or 'fn', e.g. `\(x: int) -> int { return x; }`
+fn get_lambda(iter: &mut Tokens, line: &mut usize) -> Tree {
+    let mut new_lambda = Tree{
+        line: *line,
+        end_line: *line,
+        start: iter.prev_start(),
+        end: iter.prev_end(),
+        value: TreeType::Lambda,
+        params: Vec::with_capacity(3),
+    };
+    get_signature_and_body(&mut new_lambda, iter, line);
+    new_lambda.end_line = *line;
+    new_lambda.end = iter.prev_end();
+    new_lambda
+}
 
+/// parses the parameters, return type and body of a function or lambda, adding them to new_fn
+fn get_signature_and_body(new_fn: &mut Tree, iter: &mut Tokens, line: &mut usize) {
     // check for '('
-    if next(iter, line).is_none_or(|x| x.value != TokenType::LeftParen) {
+    if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::LeftParen)) {
         panic!("({line}) expected '(' in function declaration");
     }
 
@@ -180,6 +1129,9 @@ fn get_function(iter: &mut std::iter::Peekable<core::slice::Iter<Token>>, line:
     let mut mult_params = false; // whether there are multiple params, needed for checking commas
     let mut fn_params = Tree{
         line: *line,
+        end_line: *line,
+        start: iter.prev_start(),
+        end: iter.prev_end(),
         value: TreeType::Parameters,
         params: Vec::new(),
     };
@@ -187,10 +1139,13 @@ fn get_function(iter: &mut std::iter::Peekable<core::slice::Iter<Token>>, line:
     loop {
         next_token = next(iter, line);
         // check for ')', finish params if found
-        if next_token.is_some_and(|x| x.value == TokenType::RightParen) { break } // )
+        if next_token.is_some_and(|x| x.value.same_kind(&TokenType::RightParen)) { // )
+            fn_params.end = iter.prev_end();
+            break
+        }
         // check for ',' whenever there are multiple parameters
         if mult_params {
-            if next_token.is_none_or(|x| x.value != TokenType::Comma) {
+            if next_token.is_none_or(|x| !x.value.same_kind(&TokenType::Comma)) {
                 panic!("({line}) expected ',' in function declaration");
             }
             next_token = next(iter, line);
@@ -199,6 +1154,9 @@ fn get_function(iter: &mut std::iter::Peekable<core::slice::Iter<Token>>, line:
         // ok, make param now
         let mut new_param = Tree{
             line: *line,
+            end_line: *line,
+            start: iter.prev_start(),
+            end: iter.prev_end(),
             value: TreeType::Parameter,
             params: Vec::new(),
         };
@@ -209,30 +1167,21 @@ fn get_function(iter: &mut std::iter::Peekable<core::slice::Iter<Token>>, line:
         }
         match next_token.unwrap().value {
             TokenType::Identifier(_) => {
-                new_param.params.push(Tree::leaf(next_token.unwrap().value.clone(), *line));
+                new_param.params.push(Tree::token_to_leaf(next_token.unwrap()));
             },
             _ => panic!("({line}) expected parameter name in function declaration"),
         }
 
         // check for ':'
-        if next(iter, line).is_none_or(|x| x.value != TokenType::Colon) {
+        if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::Colon)) {
             panic!("({line}) expected ':' in function declaration");
         }
 
         // param type
-        next_token = next(iter, line);
-        if next_token.is_none() {
-            panic!("({line}) expected parameter type or ')' in function declaration");
-        }
-        match next_token.unwrap().value {
-            TokenType::Int | TokenType::Float | TokenType::Bool | TokenType::String |
-            TokenType::Identifier(_) => {
-                new_param.params.push(Tree::leaf(next_token.unwrap().value.clone(), *line));
-            },
-            _ => panic!("({line}) expected parameter type or ')' in function declaration")
-        }
+        new_param.params.push(get_type("parameter type or ')' in function declaration", iter, line));
 
         // add new param to params list
+        new_param.end = iter.prev_end();
         fn_params.params.push(new_param);
         mult_params = true;
     }
@@ -243,45 +1192,152 @@ fn get_function(iter: &mut std::iter::Peekable<core::slice::Iter<Token>>, line:
     if next_token.is_none() {
         panic!("({line}) expected function body or '->'");
     }
-    let mut ret_type_value = TokenType::Void;
+    // void has no token, so its leaf is an empty span just before the body
+    let mut ret_type = Tree::leaf(TokenType::Void, *line, iter.next_start(), iter.next_start());
     match next_token.unwrap().value {
         TokenType::Arrow => {
+            ret_type = get_type("return type", iter, line);
             next_token = next(iter, line);
-            if next_token.is_none() {
-                panic!("({line}) expected return type");
-            }
-            match next_token.unwrap().value {
-                TokenType::Int | TokenType::Float | TokenType::Bool | TokenType::String |
-                TokenType::Identifier(_) => {
-                    ret_type_value = next_token.unwrap().value.clone();
-                },
-                _ => panic!("({line}) expected return type")
-            }
-            next_token = next(iter, line);
-            if next_token.is_none_or(|x| x.value != TokenType::LeftCurly) {
+            if next_token.is_none_or(|x| !x.value.same_kind(&TokenType::LeftCurly)) {
                 panic!("({line}) expected function body");
             }
         },
         TokenType::LeftCurly => {
             // do not panic
+            ret_type.start = iter.prev_start();
+            ret_type.end = iter.prev_start();
         },
         _ => panic!("({line}) expected function body or '->'")
     }
-    let ret_type = Tree::leaf(ret_type_value, *line);
+    ret_type.line = *line;
+    ret_type.end_line = *line;
     new_fn.params.push(ret_type);
 
-    // body
+    // body, which cannot break out of loops around the function
+    let loops = std::mem::take(&mut iter.loops);
+    let was_deferring = std::mem::replace(&mut iter.deferring, false);
     new_fn.params.push(get_body(iter, line));
-    new_fn
+    iter.deferring = was_deferring;
+    iter.loops = loops;
+}
+
+/// parses a type, which is either a leaf or a function type such as `fn(int, string) -> bool`
+/// expected describes what is being parsed, for the error if there is no type
+fn get_type(expected: &str, iter: &mut Tokens, line: &mut usize) -> Tree {
+    let inner = get_non_optional_type(expected, iter, line);
+    // a '?' after a '>>' that also closed the outer type arguments belongs to the outer type
+    if iter.split_shift || peek(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::Question)) {
+        return inner;
+    }
+    next(iter, line);
+    Tree{
+        line: inner.line,
+        end_line: *line,
+        start: inner.start,
+        end: iter.prev_end(),
+        value: TreeType::OptionalType,
+        params: vec![inner],
+    }
+}
+
+/// a type without a '?' after it
+fn get_non_optional_type(expected: &str, iter: &mut Tokens, line: &mut usize) -> Tree {
+    let next_token = next(iter, line);
+    match next_token.map(|x| &x.value) {
+        Some(TokenType::Int | TokenType::Float | TokenType::Bool | TokenType::String) => Tree::token_to_leaf(next_token.unwrap()),
+        Some(TokenType::Identifier(_)) => {
+            let name = Tree::token_to_leaf(next_token.unwrap());
+            if peek(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::Lt)) {
+                return name;
+            }
+            // a generic type applied to its type arguments, e.g. `Pair<int, string>`
+            next(iter, line);
+            let mut new_type = Tree{
+                line: name.line,
+                end_line: name.line,
+                start: name.start,
+                end: name.end,
+                value: TreeType::GenericType,
+                params: vec![name],
+            };
+            iter.enter(*line);
+            loop {
+                new_type.params.push(get_type("type argument", iter, line));
+                if iter.split_shift {
+                    iter.split_shift = false;
+                    break;
+                }
+                match next(iter, line).map(|x| &x.value) {
+                    Some(TokenType::Comma) => continue,
+                    Some(TokenType::Gt) => break,
+                    Some(TokenType::ShiftRight) => {
+                        iter.split_shift = true;
+                        break;
+                    },
+                    _ => panic!("({line}) expected ',' or '>' in type arguments"),
+                }
+            }
+            iter.leave();
+            new_type.end_line = *line;
+            // without the second '>' of a '>>', which closes the outer type arguments
+            new_type.end = iter.prev_end() - usize::from(iter.split_shift);
+            new_type
+        },
+        Some(TokenType::Function) => {
+            let mut new_type = Tree{
+                line: *line,
+                end_line: *line,
+                start: iter.prev_start(),
+                end: iter.prev_end(),
+                value: TreeType::FunctionType,
+                params: Vec::new(),
+            };
+            iter.enter(*line);
+            if next(iter, line).is_none_or(|x| !x.value.same_kind(&TokenType::LeftParen)) {
+                panic!("({line}) expected '(' in function type");
+            }
+            // parameter types
+            if peek(iter, line).is_some_and(|x| x.value.same_kind(&TokenType::RightParen)) {
+                next(iter, line);
+            }
+            else {
+                loop {
+                    new_type.params.push(get_type("parameter type in function type", iter, line));
+                    match next(iter, line).map(|x| &x.value) {
+                        Some(TokenType::Comma) => continue,
+                        Some(TokenType::RightParen) => break,
+                        _ => panic!("({line}) expected ',' or ')' in function type"),
+                    }
+                }
+            }
+            // return type, void unless there is an arrow
+            if peek(iter, line).is_some_and(|x| x.value.same_kind(&TokenType::Arrow)) {
+                next(iter, line);
+                new_type.params.push(get_type("return type", iter, line));
+            }
+            else {
+                new_type.params.push(Tree::leaf(TokenType::Void, *line, iter.prev_end(), iter.prev_end()));
+            }
+            iter.leave();
+            new_type.end_line = *line;
+            new_type.end = iter.prev_end() - usize::from(iter.split_shift);
+            new_type
+        },
+        _ => panic!("({line}) expected {expected}"),
+    }
 }
 
 /// grabs lines of code until an unmatched '}' is found
-fn get_body(iter: &mut std::iter::Peekable<core::slice::Iter<Token>>, line: &mut usize) -> Tree {
+fn get_body(iter: &mut Tokens, line: &mut usize) -> Tree {
     let mut body = Tree{
         line: *line,
+        end_line: *line,
+        start: iter.prev_start(),
+        end: iter.prev_end(),
         value: TreeType::Body,
         params: Vec::new(),
     };
+    iter.enter(*line);
     loop {
         let next_token = peek(iter, line);
         if next_token.is_none() {
@@ -289,17 +1345,73 @@ fn get_body(iter: &mut std::iter::Peekable<core::slice::Iter<Token>>, line: &mut
         }
         match next_token.unwrap().value {
             TokenType::RightCurly => break,
+            TokenType::Import | TokenType::Pub | TokenType::Enum => {
+                panic!("({line}) '{}' is only allowed at the top level", next_token.unwrap().value);
+            },
             _ => body.params.push(get_tree(iter, line))
         }
     }
     next(iter, line); // remove '}' from the iter
+    iter.leave();
+    body.end_line = *line;
+    body.end = iter.prev_end();
     body
 }
 
+const MAX_DEPTH: usize = 256;
+
+/// the tokens being parsed, along with how many of them have been consumed so far
+struct Tokens<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    depth: usize, // how many bodies, else ifs and (sub)expressions the parser is inside of
+    loops: Vec<Option<Symbol>>, // labels of the loops around the current tree, innermost last
+    deferring: bool, // whether the current tree is part of a defer statement
+    // whether the last '>>' closed two lists of type arguments at once, e.g. `Pair<int, Pair<int, int>>`,
+    // so the '>' of the outer list has already been consumed
+    split_shift: bool,
+}
+impl Tokens<'_> {
+    /// called on entering anything that can nest, so deeply nested input is an error rather than
+    /// overflowing the stack, each call must be paired with a call to leave
+    fn enter(&mut self, line: usize) {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            panic!("({line}) nested too deeply");
+        }
+    }
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+    /// byte offset of the next token, or of the end of the last token if there are none left
+    fn next_start(&self) -> usize {
+        match self.tokens.get(self.pos) {
+            Some(token) => token.start,
+            None => self.prev_end(),
+        }
+    }
+    /// byte offset of the last consumed token
+    fn prev_start(&self) -> usize {
+        match self.pos {
+            0 => 0,
+            pos => self.tokens[pos - 1].start,
+        }
+    }
+    /// byte offset just past the last consumed token
+    fn prev_end(&self) -> usize {
+        match self.pos {
+            0 => 0,
+            pos => self.tokens[pos - 1].end,
+        }
+    }
+}
+
 /// returns the next token from iter while also updating line number as necessary
-fn next<'a>(iter: &'a mut std::iter::Peekable<core::slice::Iter<Token>>, line: &mut usize) -> Option<&'a Token> {
-    match iter.next() {
+fn next<'a>(iter: &mut Tokens<'a>, line: &mut usize) -> Option<&'a Token> {
+    split_shift(iter, *line);
+    match iter.tokens.get(iter.pos) {
         Some(next_token) => {
+            iter.pos += 1;
             *line = next_token.line;
             // println!("token: {next_token:?}");
             Some(next_token)
@@ -309,8 +1421,9 @@ fn next<'a>(iter: &'a mut std::iter::Peekable<core::slice::Iter<Token>>, line: &
 }
 
 /// returns the next token from iter while peeking while also updating line number as necessary
-fn peek<'a>(iter: &'a mut std::iter::Peekable<core::slice::Iter<Token>>, line: &mut usize) -> Option<&'a Token> {
-    match iter.peek() {
+fn peek<'a>(iter: &Tokens<'a>, line: &mut usize) -> Option<&'a Token> {
+    split_shift(iter, *line);
+    match iter.tokens.get(iter.pos) {
         Some(next_token) => {
             *line = next_token.line;
             Some(next_token)
@@ -319,15 +1432,30 @@ fn peek<'a>(iter: &'a mut std::iter::Peekable<core::slice::Iter<Token>>, line: &
     }
 }
 
+/// only the type arguments a '>>' is nested in look past it before its second '>' is consumed,
+/// so anything else doing so means there were no type arguments for that '>' to close
+fn split_shift(iter: &Tokens, line: usize) {
+    if iter.split_shift {
+        panic!("({line}) unexpected '>'");
+    }
+}
+
+#[derive(Clone)]
 pub struct Tree {
-    line: usize, // the line on which this tree is found
-    value: TreeType,
-    params: Vec<Tree>,
+    pub line: usize, // the line on which this tree is found
+    pub end_line: usize, // the line on which this tree ends, e.g. the line of a body's '}'
+    pub start: usize, // byte offset of the first token of this tree
+    pub end: usize, // byte offset just past the last token of this tree
+    pub value: TreeType,
+    pub params: Vec<Tree>,
 }
 impl Tree {
-    fn leaf(token_type: TokenType, line: usize) -> Self {
+    fn leaf(token_type: TokenType, line: usize, start: usize, end: usize) -> Self {
         Tree{
             line,
+            end_line: line,
+            start,
+            end,
             value: TreeType::Leaf(token_type),
             params: Vec::new()
         }
@@ -335,6 +1463,9 @@ impl Tree {
     fn token_to_leaf(token: &Token) -> Self {
         Tree{
             line: token.line,
+            end_line: token.line,
+            start: token.start,
+            end: token.end,
             value: TreeType::Leaf(token.value.clone()),
             params: Vec::new()
         }
@@ -381,10 +1512,39 @@ pub enum TreeType {
     File, // wraps the entire input file into a single expr
     Body, // contains 0 or more lines of code
     Leaf(TokenType), // any type of leaf, TODO: come up with a better name (terminator? (er?))
-    Assign, Expression,
-    Enum, Struct, // replace these with generic TypeDeclare?
-    Function, Parameters, Parameter, Return,
-    If, While,
+    Assign, Reassign, Expression, // Assign: [var or const leaf, name leaf, type, value?]
+    Inferred, // the type of an Assign without one, which comes from its value
+    BinaryOp(TokenType), UnaryOp(TokenType), // the operator, with its operand(s) as params
+    Call, Arguments, // Call: [callee, Arguments]
+    Enum, Struct, // replace these with generic TypeDeclare? Enum: [name leaf, Variant...]
+    Variant, // [name leaf, field type leaves...]
+    // Struct: [name leaf, TypeParameters, Field...]
+    Field, // [name leaf, type]
+    TypeParameters, // [name leaves...], of a Struct or at the end of a generic Function
+    GenericType, // [name leaf, type arguments...], e.g. `Pair<int, string>`
+    Function, // [name leaf, Parameters, return type, Body, TypeParameters?]
+    Parameters, Parameter, Return,
+    Lambda, // [Parameters, return type, Body], like a Function without a name
+    FunctionType, // [parameter types..., return type]
+    OptionalType, // [type], e.g. `int?`, which can also be none
+    If, While, // If: [condition Expression or Let, Body, else If or Body?]
+    Let, // [name leaf, Expression], which binds name to the value of an optional that is not none
+    Defer, // [statement]
+    Try, // [Body, name leaf, catch Body]
+    Throw, // [Expression], the message or the Error being thrown
+    For, // [variable leaf, Range or Expression, Body]
+    Range(TokenType), // the '..' or '..=' token, with the start and end as params
+    Forever, // [Body]
+    Switch, // [Expression, Arm...]
+    Arm, // [Patterns, Body, guard Expression?]
+    Patterns, // [pattern...], any of which can match
+    VariantPattern, // [enum name leaf, variant name leaf, field patterns...]
+    Array, // [elements...], an array literal
+    Label, // [name leaf, loop]
+    Break, Continue, // [label leaf?]
+    Import, // [module name or path leaf]
+    Pub, // [Function, Assign, Enum or Struct], which is exported from its module
+    Member, // [module, name leaf], e.g. `utils.func`
 }
 impl PartialEq for TreeType {
     fn eq(&self, other: &Self) -> bool {
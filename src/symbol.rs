@@ -0,0 +1,146 @@
+//! interned identifiers, every distinct name is stored once and referred to by a small id so that
+//! names are cheap to copy and compare
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{LazyLock, Mutex, OnceLock};
+
+/// an interned string, two symbols are equal exactly when the strings they were made from are
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+/// the most bytes of names that are interned before interning another is an error
+///
+/// interned strings are never freed, since a symbol can be kept anywhere. a single run only sees
+/// the names in its source, but the language server relexes a document on every change, so every
+/// half typed name stays interned until it exits. this keeps that from growing without bound, at
+/// the cost of the server reporting an error on every change once it is reached
+pub const MAX_INTERNED_BYTES: usize = 64 << 20;
+
+struct Interner {
+    ids: HashMap<&'static str, Symbol>,
+    bytes: usize, // the total length of every interned string
+}
+
+// shared by every thread so symbols can be passed between them, only interning takes the lock
+static INTERNER: LazyLock<Mutex<Interner>> = LazyLock::new(|| Mutex::new(Interner{
+    ids: HashMap::new(),
+    bytes: 0,
+}));
+
+// the strings by symbol id, in chunks that double in size so that a chunk never moves once it is
+// allocated, which lets as_str read them without taking the lock
+const FIRST_CHUNK_BITS: u32 = 8;
+const CHUNKS: usize = 24;
+static STRINGS: [OnceLock<Box<[OnceLock<&'static str>]>>; CHUNKS] = [const { OnceLock::new() }; CHUNKS];
+
+/// the chunk of STRINGS the string of a symbol id is in, and where in that chunk
+fn locate(id: u32) -> (usize, usize) {
+    let n = id as usize + (1 << FIRST_CHUNK_BITS);
+    let bits = n.ilog2();
+    ((bits - FIRST_CHUNK_BITS) as usize, n - (1 << bits))
+}
+
+impl Symbol {
+    /// the symbol for string, only allocates the first time a string is seen
+    pub fn intern(string: &str) -> Symbol {
+        let mut interner = INTERNER.lock().unwrap();
+        if let Some(symbol) = interner.ids.get(string) {
+            return *symbol;
+        }
+        if interner.bytes + string.len() > MAX_INTERNED_BYTES {
+            // let go of the lock first, so that it is not poisoned for every later caller
+            drop(interner);
+            panic!("too many distinct names, at most {MAX_INTERNED_BYTES} bytes of them can be used");
+        }
+        let id = interner.ids.len() as u32;
+        let (chunk, offset) = locate(id);
+        let string: &'static str = Box::leak(string.to_string().into_boxed_str());
+        STRINGS[chunk]
+            .get_or_init(|| (0..1usize << (chunk as u32 + FIRST_CHUNK_BITS)).map(|_| OnceLock::new()).collect())[offset]
+            .set(string)
+            .expect("symbol ids are only handed out once");
+        interner.bytes += string.len();
+        interner.ids.insert(string, Symbol(id));
+        Symbol(id)
+    }
+
+    /// the symbol for string if it has already been interned, without interning it otherwise
+    /// for looking up names from outside of a script, which only exist if the script used them
+    pub fn lookup(string: &str) -> Option<Symbol> {
+        INTERNER.lock().unwrap().ids.get(string).copied()
+    }
+
+    pub fn as_str(self) -> &'static str {
+        let (chunk, offset) = locate(self.0);
+        STRINGS[chunk].get().and_then(|x| x[offset].get()).expect("symbols are only made by interning")
+    }
+}
+
+impl std::fmt::Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+impl std::fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+/// maps names to values through nested scopes, inner scopes shadow outer ones
+pub struct SymbolTable<T> {
+    scopes: Vec<HashMap<Symbol, T>>, // innermost scope last, there is always at least one
+}
+
+impl<T> SymbolTable<T> {
+    pub fn new() -> Self {
+        SymbolTable{
+            scopes: vec![HashMap::new()],
+        }
+    }
+
+    pub fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    /// drops the innermost scope along with everything declared in it
+    pub fn pop_scope(&mut self) {
+        if self.scopes.len() > 1 {
+            self.scopes.pop();
+        }
+    }
+
+    /// declares name in the innermost scope, returning what it replaced in that scope
+    pub fn insert(&mut self, name: Symbol, value: T) -> Option<T> {
+        self.scopes.last_mut().unwrap().insert(name, value)
+    }
+
+    /// the innermost declaration of name
+    pub fn get(&self, name: Symbol) -> Option<&T> {
+        self.scopes.iter().rev().find_map(|x| x.get(&name))
+    }
+
+    pub fn get_mut(&mut self, name: Symbol) -> Option<&mut T> {
+        self.scopes.iter_mut().rev().find_map(|x| x.get_mut(&name))
+    }
+
+    /// every declaration that get would find, innermost scope first
+    pub fn visible(&self) -> impl Iterator<Item = (Symbol, &T)> {
+        let mut seen = HashSet::new();
+        self.scopes.iter().rev()
+            .flat_map(|x| x.iter())
+            .filter(move |(name, _)| seen.insert(**name))
+            .map(|(name, value)| (*name, value))
+    }
+
+    /// whether name is declared in the innermost scope, ignoring outer ones
+    pub fn in_current_scope(&self, name: Symbol) -> bool {
+        self.scopes.last().unwrap().contains_key(&name)
+    }
+}
+
+impl<T> Default for SymbolTable<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
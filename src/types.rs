@@ -0,0 +1,922 @@
+//! checks the types of a parsed file before it is run, so that a value of the wrong type is
+//! reported even if the code it is in never runs
+//!
+//! names whose declarations the checker cannot see, like builtins, natives and the members of
+//! imported modules, have an unknown type that is compatible with everything, the interpreter
+//! still checks those when they are used
+//!
+//! it also checks that locals declared without a value are assigned one on every path before they
+//! are read
+
+use std::collections::{HashMap, HashSet};
+
+use crate::check;
+use crate::diagnostic::Diagnostic;
+use crate::interpreter::ERROR;
+use crate::lexer::TokenType;
+use crate::parser::{Tree, TreeType};
+use crate::symbol::{Symbol, SymbolTable};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Type {
+    Int, Float, Bool, String, Void,
+    Array, // arrays do not have an element type yet
+    Named(Symbol, Vec<Type>), // an enum or struct, with its type arguments
+    Function(Vec<Type>, Box<Type>), // parameter types and return type
+    Optional(Box<Type>), // a value of the type, or none
+    Param(Symbol), // a type parameter of the declaration being checked
+    Var(usize), // a type that is inferred from how it is used, like a type argument at a call
+    Unknown, // could be anything, so it is never wrong
+}
+
+/// type errors in a File tree, in the order of the trees they are about
+pub fn check(file: &Tree) -> Vec<Diagnostic> {
+    let mut checker = Checker{
+        functions: HashMap::new(),
+        structs: HashMap::new(),
+        enums: HashMap::new(),
+        imports: HashSet::new(),
+        globals: HashMap::new(),
+        locals: SymbolTable::new(),
+        generics: Vec::new(),
+        function: None,
+        vars: Vec::new(),
+        unassigned: Vec::new(),
+        errors: Vec::new(),
+    };
+    let items: Vec<&Tree> = file.params.iter().map(|x| match x.value {
+        TreeType::Pub => &x.params[0],
+        _ => x,
+    }).collect();
+
+    // declarations are visible to everything in the file, wherever they are
+    for item in &items {
+        match item.value {
+            TreeType::Function => { checker.functions.insert(name(&item.params[0]), *item); },
+            TreeType::Struct => { checker.structs.insert(name(&item.params[0]), *item); },
+            TreeType::Enum => { checker.enums.insert(name(&item.params[0]), *item); },
+            TreeType::Import => {
+                let module = match &item.params[0].value {
+                    TreeType::Leaf(TokenType::Identifier(name)) => Some(*name),
+                    TreeType::Leaf(TokenType::StringLit(path)) => std::path::Path::new(path)
+                        .file_stem().and_then(|x| x.to_str()).map(Symbol::intern),
+                    _ => None,
+                };
+                checker.imports.extend(module);
+            },
+            _ => {},
+        }
+    }
+    for item in &items {
+        if item.value == TreeType::Assign && item.params[2].value != TreeType::Inferred {
+            let type_name = checker.type_of(&item.params[2]);
+            checker.globals.insert(name(&item.params[1]), type_name);
+        }
+    }
+    // globals without a type take it from their value, which can only use the globals before them
+    for item in &items {
+        if item.value == TreeType::Assign && item.params[2].value == TreeType::Inferred {
+            let type_name = checker.declaration(item);
+            checker.globals.insert(name(&item.params[1]), type_name);
+        }
+    }
+
+    for item in items {
+        match item.value {
+            TreeType::Function => checker.function(item),
+            TreeType::Struct => {
+                checker.generics = item.params[1].params.iter().map(name).collect();
+                for field in &item.params[2..] {
+                    checker.type_of(&field.params[1]);
+                }
+                checker.generics.clear();
+            },
+            TreeType::Enum => {
+                for variant in &item.params[1..] {
+                    for field in &variant.params[1..] {
+                        checker.type_of(field);
+                    }
+                }
+            },
+            // the global was declared above, so only its value is left to check
+            TreeType::Assign => {
+                if let Some(exp) = item.params.get(3)
+                    && item.params[2].value != TreeType::Inferred {
+                    let declared = checker.globals[&name(&item.params[1])].clone();
+                    let found = checker.expression(exp);
+                    checker.expect(&declared, &found, item.line, |x, y| {
+                        format!("expected {x} for '{}', found {y}", name(&item.params[1]))
+                    });
+                }
+            },
+            TreeType::Import => {},
+            _ => checker.statement(item),
+        }
+    }
+    checker.errors
+}
+
+struct Checker<'a> {
+    functions: HashMap<Symbol, &'a Tree>,
+    structs: HashMap<Symbol, &'a Tree>,
+    enums: HashMap<Symbol, &'a Tree>,
+    imports: HashSet<Symbol>, // names of imported modules
+    globals: HashMap<Symbol, Type>,
+    locals: SymbolTable<Type>,
+    generics: Vec<Symbol>, // type parameters that types can refer to
+    function: Option<(String, Type)>, // how the function being checked is referred to, and its return type
+    vars: Vec<Option<Type>>, // what each Var has been inferred to be so far
+    unassigned: Vec<Symbol>, // locals declared without a value that might not have been assigned one yet
+    errors: Vec<Diagnostic>,
+}
+
+impl Checker<'_> {
+    fn error(&mut self, line: usize, message: String) {
+        self.errors.push(Diagnostic::new(Some(line), message));
+    }
+
+    /// reports the message made from the expected and found types if they are not compatible
+    fn expect(&mut self, expected: &Type, found: &Type, line: usize, message: impl FnOnce(String, String) -> String) {
+        if !self.unify(expected, found) {
+            let message = message(self.display(expected), self.display(found));
+            self.error(line, message);
+        }
+    }
+
+    /// the type a type tree names
+    fn type_of(&mut self, tree: &Tree) -> Type {
+        match &tree.value {
+            TreeType::Leaf(TokenType::Int) => Type::Int,
+            TreeType::Leaf(TokenType::Float) => Type::Float,
+            TreeType::Leaf(TokenType::Bool) => Type::Bool,
+            TreeType::Leaf(TokenType::String) => Type::String,
+            TreeType::Leaf(TokenType::Void) => Type::Void,
+            TreeType::Leaf(TokenType::Identifier(name)) => {
+                if self.generics.contains(name) {
+                    return Type::Param(*name);
+                }
+                if name.as_str() == "array" {
+                    return Type::Array;
+                }
+                match self.structs.get(name) {
+                    // the type arguments of a generic struct can be left out
+                    Some(declaration) => Type::Named(*name, vec![Type::Unknown; declaration.params[1].params.len()]),
+                    None if self.enums.contains_key(name) || name.as_str() == ERROR => Type::Named(*name, Vec::new()),
+                    None => Type::Unknown,
+                }
+            },
+            TreeType::FunctionType => {
+                let (ret_type, params) = tree.params.split_last().unwrap();
+                let params = params.iter().map(|x| self.type_of(x)).collect();
+                Type::Function(params, Box::new(self.type_of(ret_type)))
+            },
+            TreeType::OptionalType => Type::Optional(Box::new(self.type_of(&tree.params[0]))),
+            TreeType::GenericType => {
+                let type_name = name(&tree.params[0]);
+                let args: Vec<Type> = tree.params[1..].iter().map(|x| self.type_of(x)).collect();
+                let expected = match self.structs.get(&type_name) {
+                    Some(declaration) => declaration.params[1].params.len(),
+                    None if self.enums.contains_key(&type_name) || self.generics.contains(&type_name) => 0,
+                    None => return Type::Unknown,
+                };
+                if args.len() != expected {
+                    self.error(tree.line, format!("'{type_name}' takes {expected} type arguments but {} were given", args.len()));
+                    return Type::Unknown;
+                }
+                Type::Named(type_name, args)
+            },
+            _ => Type::Unknown,
+        }
+    }
+
+    /// a new type to be inferred
+    fn var(&mut self) -> Type {
+        self.vars.push(None);
+        Type::Var(self.vars.len() - 1)
+    }
+
+    /// follows what Vars have been inferred to be until reaching something else
+    fn shallow(&self, t: &Type) -> Type {
+        match t {
+            Type::Var(var) => match &self.vars[*var] {
+                Some(inferred) => self.shallow(inferred),
+                None => t.clone(),
+            },
+            _ => t.clone(),
+        }
+    }
+
+    /// t with every Var that has been inferred replaced with what it was inferred to be
+    fn resolve(&self, t: &Type) -> Type {
+        match self.shallow(t) {
+            Type::Named(name, args) => Type::Named(name, args.iter().map(|x| self.resolve(x)).collect()),
+            Type::Function(params, ret) => Type::Function(params.iter().map(|x| self.resolve(x)).collect(),
+                Box::new(self.resolve(&ret))),
+            Type::Optional(t) => Type::Optional(Box::new(self.resolve(&t))),
+            t => t,
+        }
+    }
+
+    /// whether a value of type found can be used where expected is, inferring Vars to make them so
+    fn unify(&mut self, expected: &Type, found: &Type) -> bool {
+        match (self.shallow(expected), self.shallow(found)) {
+            (Type::Unknown, _) | (_, Type::Unknown) => true,
+            (Type::Var(a), Type::Var(b)) if a == b => true,
+            (Type::Var(var), t) | (t, Type::Var(var)) => {
+                self.vars[var] = Some(t);
+                true
+            },
+            (Type::Named(a, a_args), Type::Named(b, b_args)) => {
+                a == b && a_args.len() == b_args.len() && a_args.iter().zip(&b_args).all(|(x, y)| self.unify(x, y))
+            },
+            (Type::Function(a_params, a_ret), Type::Function(b_params, b_ret)) => {
+                a_params.len() == b_params.len()
+                    && a_params.iter().zip(&b_params).all(|(x, y)| self.unify(x, y))
+                    && self.unify(&a_ret, &b_ret)
+            },
+            (Type::Optional(a), Type::Optional(b)) => self.unify(&a, &b),
+            // a value can be used where an optional of its type is expected
+            (Type::Optional(a), b) => self.unify(&a, &b),
+            (a, b) => a == b,
+        }
+    }
+
+    /// t with the type parameters in args replaced with their types
+    fn substitute(&self, t: &Type, args: &HashMap<Symbol, Type>) -> Type {
+        match t {
+            Type::Param(name) => args.get(name).cloned().unwrap_or(Type::Param(*name)),
+            Type::Named(name, type_args) => Type::Named(*name, type_args.iter().map(|x| self.substitute(x, args)).collect()),
+            Type::Function(params, ret) => Type::Function(params.iter().map(|x| self.substitute(x, args)).collect(),
+                Box::new(self.substitute(ret, args))),
+            Type::Optional(t) => Type::Optional(Box::new(self.substitute(t, args))),
+            t => t.clone(),
+        }
+    }
+
+    /// how a type is written in source, with _ for anything that is not known
+    fn display(&self, t: &Type) -> String {
+        match self.resolve(t) {
+            Type::Int => String::from("int"),
+            Type::Float => String::from("float"),
+            Type::Bool => String::from("bool"),
+            Type::String => String::from("string"),
+            Type::Void => String::from("void"),
+            Type::Array => String::from("array"),
+            Type::Named(name, args) if args.is_empty() => name.to_string(),
+            Type::Named(name, args) => {
+                let args: Vec<String> = args.iter().map(|x| self.display(x)).collect();
+                format!("{name}<{}>", args.join(", "))
+            },
+            Type::Function(params, ret) => {
+                let params: Vec<String> = params.iter().map(|x| self.display(x)).collect();
+                match *ret {
+                    Type::Void => format!("fn({})", params.join(", ")),
+                    ret => format!("fn({}) -> {}", params.join(", "), self.display(&ret)),
+                }
+            },
+            Type::Optional(t) => format!("{}?", self.display(&t)),
+            Type::Param(name) => name.to_string(),
+            Type::Var(_) | Type::Unknown => String::from("_"),
+        }
+    }
+
+    /// whether t still has Vars in it that nothing has inferred
+    fn is_ambiguous(&self, t: &Type) -> bool {
+        match self.resolve(t) {
+            Type::Var(_) => true,
+            Type::Named(_, args) => args.iter().any(|x| self.is_ambiguous(x)),
+            Type::Function(params, ret) => params.iter().any(|x| self.is_ambiguous(x)) || self.is_ambiguous(&ret),
+            Type::Optional(t) => self.is_ambiguous(&t),
+            _ => false,
+        }
+    }
+
+    /// whether nothing is known about a value of type t, so anything can be done with it
+    fn is_open(&self, t: &Type) -> bool {
+        matches!(self.shallow(t), Type::Unknown | Type::Var(_) | Type::Param(_))
+    }
+
+    /// the parameter and return types of a declared function, with its type parameters replaced
+    /// by new Vars to be inferred at a call
+    fn signature(&mut self, function: &Tree) -> (Vec<Type>, Type) {
+        let type_params: Vec<Symbol> = function.params.get(4).map_or(Vec::new(), |x| x.params.iter().map(name).collect());
+        let outer = std::mem::replace(&mut self.generics, type_params.clone());
+        let params: Vec<Type> = function.params[1].params.iter().map(|x| self.type_of(&x.params[1])).collect();
+        let ret = self.type_of(&function.params[2]);
+        self.generics = outer;
+        let args: HashMap<Symbol, Type> = type_params.into_iter().map(|x| (x, self.var())).collect();
+        (params.iter().map(|x| self.substitute(x, &args)).collect(), self.substitute(&ret, &args))
+    }
+
+    fn function(&mut self, function: &Tree) {
+        self.generics = function.params.get(4).map_or(Vec::new(), |x| x.params.iter().map(name).collect());
+        let ret = self.type_of(&function.params[2]);
+        let outer = self.function.replace((format!("'{}'", name(&function.params[0])), ret));
+        self.unassigned.clear();
+        self.locals.push_scope();
+        for param in &function.params[1].params {
+            let type_name = self.type_of(&param.params[1]);
+            self.locals.insert(name(&param.params[0]), type_name);
+        }
+        self.body(&function.params[3]);
+        self.locals.pop_scope();
+        self.function = outer;
+        self.generics.clear();
+    }
+
+    fn body(&mut self, body: &Tree) {
+        self.locals.push_scope();
+        for statement in &body.params {
+            self.statement(statement);
+        }
+        self.locals.pop_scope();
+    }
+
+    /// checks a body that might not run, returning what is unassigned after it, or None if it
+    /// never finishes normally, with what was unassigned before it put back
+    fn branch(&mut self, body: &Tree) -> Option<Vec<Symbol>> {
+        let before = self.unassigned.clone();
+        self.body(body);
+        let after = std::mem::replace(&mut self.unassigned, before);
+        (!diverges(body)).then_some(after)
+    }
+
+    /// what is unassigned after one of branches has run, which is anything that one of them that
+    /// finishes normally leaves unassigned
+    fn join(&mut self, branches: Vec<Option<Vec<Symbol>>>) {
+        let before = std::mem::take(&mut self.unassigned);
+        let finished: Vec<Vec<Symbol>> = branches.into_iter().flatten().collect();
+        if finished.is_empty() {
+            self.unassigned = before;
+            return;
+        }
+        // locals declared in the branches have gone out of scope
+        self.unassigned = before.into_iter().filter(|x| finished.iter().any(|y| y.contains(x))).collect();
+    }
+
+    /// checks the value of an Assign tree against its type, and returns the type of the variable,
+    /// which is the type of the value if it has none
+    fn declaration(&mut self, tree: &Tree) -> Type {
+        let variable = name(&tree.params[1]);
+        if tree.params[2].value != TreeType::Inferred {
+            let declared = self.type_of(&tree.params[2]);
+            if let Some(exp) = tree.params.get(3) {
+                let found = self.expression(exp);
+                self.expect(&declared, &found, tree.line, |x, y| format!("expected {x} for '{variable}', found {y}"));
+            }
+            return declared;
+        }
+        let Some(exp) = tree.params.get(3) else {
+            self.error(tree.line, format!("'{variable}' needs a type or a value"));
+            return Type::Unknown;
+        };
+        let found = self.expression(exp);
+        if self.is_ambiguous(&found) {
+            let message = match self.resolve(&found) {
+                // the optional of nothing in particular, which is only ever none
+                Type::Optional(inner) if matches!(self.resolve(&inner), Type::Var(_)) => {
+                    format!("'{variable}' needs a type, none alone does not say which optional it is")
+                },
+                _ => format!("cannot infer a type for '{variable}' from {}, it needs a type", self.display(&found)),
+            };
+            self.error(tree.line, message);
+            return Type::Unknown;
+        }
+        match self.resolve(&found) {
+            Type::Void => {
+                self.error(tree.line, format!("'{variable}' cannot be void"));
+                Type::Unknown
+            },
+            found => found,
+        }
+    }
+
+    fn statement(&mut self, tree: &Tree) {
+        match tree.value {
+            TreeType::Assign => {
+                let declared = self.declaration(tree);
+                let variable = name(&tree.params[1]);
+                self.unassigned.retain(|x| *x != variable);
+                if tree.params.len() < 4 {
+                    self.unassigned.push(variable);
+                }
+                self.locals.insert(variable, declared);
+            },
+            TreeType::Reassign => {
+                let found = self.expression(&tree.params[1]);
+                let variable = name(&tree.params[0]);
+                self.unassigned.retain(|x| *x != variable);
+                let declared = match self.locals.get(variable).or_else(|| self.globals.get(&variable)) {
+                    Some(declared) => declared.clone(),
+                    None => return,
+                };
+                self.expect(&declared, &found, tree.line, |x, y| format!("expected {x} for '{variable}', found {y}"));
+            },
+            TreeType::Expression => {
+                self.expression(tree);
+            },
+            TreeType::If => {
+                self.locals.push_scope();
+                if tree.params[0].value == TreeType::Let {
+                    let unwrapped = self.unwrap(&tree.params[0]);
+                    self.locals.insert(name(&tree.params[0].params[0]), unwrapped);
+                }
+                else {
+                    self.condition(&tree.params[0]);
+                }
+                let then = self.branch(&tree.params[1]);
+                self.locals.pop_scope();
+                let other = match tree.params.get(2) {
+                    Some(other) if other.value == TreeType::If => {
+                        let before = self.unassigned.clone();
+                        self.statement(other);
+                        Some(std::mem::replace(&mut self.unassigned, before))
+                    },
+                    Some(other) => self.branch(other),
+                    None => Some(self.unassigned.clone()),
+                };
+                self.join(vec![then, other]);
+            },
+            // loop bodies might not run at all, so nothing they assign counts after them
+            TreeType::While => {
+                self.condition(&tree.params[0]);
+                self.branch(&tree.params[1]);
+            },
+            TreeType::For => {
+                let element = self.iterable(&tree.params[1]);
+                self.locals.push_scope();
+                self.locals.insert(name(&tree.params[0]), element);
+                self.branch(&tree.params[2]);
+                self.locals.pop_scope();
+            },
+            // except a forever body, which always runs up to the first statement that can break out
+            TreeType::Forever => {
+                let before = self.unassigned.clone();
+                let mut leaving = None;
+                self.locals.push_scope();
+                for statement in &tree.params[0].params {
+                    if leaving.is_none() && breaks(statement, false) {
+                        leaving = Some(self.unassigned.clone());
+                    }
+                    self.statement(statement);
+                }
+                self.locals.pop_scope();
+                self.unassigned = before;
+                self.join(vec![leaving]);
+            },
+            // the body can stop at any point, so the catch body cannot rely on anything it assigned
+            TreeType::Try => {
+                let body = self.branch(&tree.params[0]);
+                self.locals.push_scope();
+                self.locals.insert(name(&tree.params[1]), Type::Named(Symbol::intern(ERROR), Vec::new()));
+                let catch = self.branch(&tree.params[2]);
+                self.locals.pop_scope();
+                self.join(vec![body, catch]);
+            },
+            TreeType::Throw => {
+                let found = self.expression(&tree.params[0]);
+                match self.shallow(&found) {
+                    Type::String => {},
+                    Type::Named(name, _) if name.as_str() == ERROR => {},
+                    _ if self.is_open(&found) => {},
+                    _ => {
+                        let message = format!("expected string or {ERROR} for throw, found {}", self.display(&found));
+                        self.error(tree.line, message);
+                    },
+                }
+            },
+            TreeType::Label => self.statement(&tree.params[1]),
+            // deferred statements run at the end of the body, after whatever follows them
+            TreeType::Defer => {
+                let before = self.unassigned.clone();
+                self.statement(&tree.params[0]);
+                self.unassigned = before;
+            },
+            TreeType::Return => {
+                let found = match tree.params.first() {
+                    Some(exp) => self.expression(exp),
+                    None => Type::Void,
+                };
+                if let Some((function, ret)) = self.function.clone() {
+                    self.expect(&ret, &found, tree.line, |x, y| format!("expected {function} to return {x}, found {y}"));
+                }
+            },
+            TreeType::Switch => {
+                let scrutinee = self.expression(&tree.params[0]);
+                let mut branches = Vec::new();
+                for arm in &tree.params[1..] {
+                    self.locals.push_scope();
+                    for pattern in &arm.params[0].params {
+                        self.pattern(pattern, &scrutinee);
+                    }
+                    if let Some(guard) = arm.params.get(2) {
+                        self.condition(guard);
+                    }
+                    branches.push(self.branch(&arm.params[1]));
+                    self.locals.pop_scope();
+                }
+                // unless an arm matches anything or every variant is handled, none of them might run
+                let catch_all = tree.params[1..].iter().any(|arm| arm.params.len() < 3
+                    && arm.params[0].params.iter().any(|x| matches!(x.value, TreeType::Leaf(TokenType::Identifier(_)))));
+                if !catch_all && check::unhandled_variants(tree, &self.enums).is_none_or(|x| !x.is_empty()) {
+                    branches.push(Some(self.unassigned.clone()));
+                }
+                self.join(branches);
+            },
+            _ => {},
+        }
+    }
+
+    /// the type of the value a Let tree binds, which is what the optional it unwraps is an optional of
+    fn unwrap(&mut self, tree: &Tree) -> Type {
+        let found = self.expression(&tree.params[1]);
+        match self.shallow(&found) {
+            Type::Optional(inner) => *inner,
+            _ if self.is_open(&found) => Type::Unknown,
+            _ => {
+                let message = format!("expected an optional for 'if let', found {}", self.display(&found));
+                self.error(tree.line, message);
+                Type::Unknown
+            },
+        }
+    }
+
+    /// checks that an if, while or guard condition is a bool
+    fn condition(&mut self, exp: &Tree) {
+        let found = self.expression(exp);
+        self.expect(&Type::Bool, &found, exp.line, |_, y| format!("expected bool for condition, found {y}"));
+    }
+
+    /// the type of the values a for loop goes through
+    fn iterable(&mut self, tree: &Tree) -> Type {
+        if let TreeType::Range(_) = tree.value {
+            for bound in &tree.params {
+                let found = self.expression(bound);
+                self.expect(&Type::Int, &found, bound.line, |_, y| format!("expected int for range bound, found {y}"));
+            }
+            return Type::Int;
+        }
+        let found = self.expression(tree);
+        match self.shallow(&found) {
+            Type::String => Type::String,
+            Type::Array => Type::Unknown,
+            _ if self.is_open(&found) => Type::Unknown,
+            _ => {
+                let message = format!("cannot iterate over {}", self.display(&found));
+                self.error(tree.line, message);
+                Type::Unknown
+            },
+        }
+    }
+
+    /// declares the names a pattern binds, given the type of the value it is matched against
+    fn pattern(&mut self, pattern: &Tree, t: &Type) {
+        match &pattern.value {
+            TreeType::Leaf(TokenType::Identifier(name)) => {
+                self.locals.insert(*name, t.clone());
+            },
+            TreeType::VariantPattern => {
+                let fields = self.variant_fields(&pattern.params[0], name(&pattern.params[1]));
+                for (i, field) in pattern.params[2..].iter().enumerate() {
+                    let field_type = fields.as_ref().and_then(|x| x.get(i).cloned()).unwrap_or(Type::Unknown);
+                    self.pattern(field, &field_type);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// the field types of a variant of the enum that tree names, None if the enum is not declared
+    /// in this file or has no such variant
+    fn variant_fields(&mut self, tree: &Tree, variant: Symbol) -> Option<Vec<Type>> {
+        let declaration = match &tree.value {
+            TreeType::Leaf(TokenType::Identifier(name)) => *self.enums.get(name)?,
+            _ => return None,
+        };
+        let found = declaration.params[1..].iter().find(|x| name(&x.params[0]) == variant)?;
+        Some(found.params[1..].iter().map(|x| self.type_of(x)).collect())
+    }
+
+    /// whether tree is the name of an enum, rather than of a variable
+    fn is_enum(&self, tree: &Tree) -> bool {
+        matches!(&tree.value, TreeType::Leaf(TokenType::Identifier(name))
+            if self.enums.contains_key(name) && !self.is_variable(*name))
+    }
+
+    /// whether tree is the name of an imported module, rather than of a variable
+    fn is_module(&self, tree: &Tree) -> bool {
+        matches!(&tree.value, TreeType::Leaf(TokenType::Identifier(name))
+            if self.imports.contains(name) && !self.is_variable(*name))
+    }
+
+    fn is_variable(&self, name: Symbol) -> bool {
+        self.locals.get(name).is_some() || self.globals.contains_key(&name)
+    }
+
+    fn expression(&mut self, tree: &Tree) -> Type {
+        match &tree.value {
+            TreeType::Expression => self.expression(&tree.params[0]),
+            TreeType::Leaf(token) => match token {
+                TokenType::IntLit(_) => Type::Int,
+                TokenType::FloatLit(_) => Type::Float,
+                TokenType::BoolLit(_) => Type::Bool,
+                TokenType::StringLit(_) => Type::String,
+                TokenType::NoneLit => Type::Optional(Box::new(self.var())),
+                TokenType::Identifier(name) => {
+                    if let Some(t) = self.locals.get(*name).cloned() {
+                        if let Some(i) = self.unassigned.iter().position(|x| x == name) {
+                            // reported once, rather than at every use after
+                            self.unassigned.remove(i);
+                            self.error(tree.line, format!("'{name}' is used before being assigned"));
+                        }
+                        return t;
+                    }
+                    if let Some(t) = self.globals.get(name) {
+                        return t.clone();
+                    }
+                    match self.functions.get(name) {
+                        Some(function) => {
+                            let (params, ret) = self.signature(function);
+                            Type::Function(params, Box::new(ret))
+                        },
+                        None => Type::Unknown,
+                    }
+                },
+                _ => Type::Unknown,
+            },
+            TreeType::Member => {
+                let member = name(&tree.params[1]);
+                if self.is_enum(&tree.params[0]) {
+                    return self.variant(&tree.params[0], member, &[], tree.line);
+                }
+                if self.is_module(&tree.params[0]) {
+                    return Type::Unknown;
+                }
+                let found = self.expression(&tree.params[0]);
+                self.field(&found, member, tree.line)
+            },
+            TreeType::Array => {
+                for element in &tree.params {
+                    self.expression(element);
+                }
+                Type::Array
+            },
+            TreeType::Lambda => {
+                let ret = self.type_of(&tree.params[1]);
+                let outer = self.function.replace((String::from("lambda"), ret.clone()));
+                // a lambda can see the locals around it, which might be assigned by the time it is called
+                let unassigned = std::mem::take(&mut self.unassigned);
+                self.locals.push_scope();
+                let mut params = Vec::new();
+                for param in &tree.params[0].params {
+                    let type_name = self.type_of(&param.params[1]);
+                    self.locals.insert(name(&param.params[0]), type_name.clone());
+                    params.push(type_name);
+                }
+                self.body(&tree.params[2]);
+                self.locals.pop_scope();
+                self.unassigned = unassigned;
+                self.function = outer;
+                Type::Function(params, Box::new(ret))
+            },
+            TreeType::UnaryOp(op) => {
+                let operand = self.expression(&tree.params[0]);
+                match (op, self.shallow(&operand)) {
+                    (TokenType::Minus, t @ (Type::Int | Type::Float)) => t,
+                    (TokenType::Not, Type::Bool) => Type::Bool,
+                    (TokenType::BwNot, Type::Int) => Type::Int,
+                    _ if self.is_open(&operand) => operand,
+                    _ => {
+                        let message = format!("cannot apply '{op}' to {}", self.display(&operand));
+                        self.error(tree.line, message);
+                        Type::Unknown
+                    },
+                }
+            },
+            TreeType::BinaryOp(TokenType::Coalesce) => {
+                let lhs = self.expression(&tree.params[0]);
+                let rhs = self.expression(&tree.params[1]);
+                let inner = match self.shallow(&lhs) {
+                    Type::Optional(inner) => *inner,
+                    _ if self.is_open(&lhs) => return rhs,
+                    _ => {
+                        let message = format!("cannot apply '??' to {}, which is not optional", self.display(&lhs));
+                        self.error(tree.line, message);
+                        return rhs;
+                    },
+                };
+                // the default can be another optional, which makes the result one too
+                if let Type::Optional(_) = self.shallow(&rhs) {
+                    let optional = Type::Optional(Box::new(inner));
+                    self.expect(&optional, &rhs, tree.line, |x, y| format!("expected {x} for default of '??', found {y}"));
+                    return optional;
+                }
+                self.expect(&inner, &rhs, tree.line, |x, y| format!("expected {x} for default of '??', found {y}"));
+                inner
+            },
+            TreeType::BinaryOp(op) => {
+                let lhs = self.expression(&tree.params[0]);
+                let rhs = self.expression(&tree.params[1]);
+                self.binary(op, &lhs, &rhs, tree.line)
+            },
+            TreeType::Call => {
+                let args: Vec<Type> = tree.params[1].params.iter().map(|x| self.expression(x)).collect();
+                self.call(&tree.params[0], &args, tree.line)
+            },
+            _ => Type::Unknown,
+        }
+    }
+
+    /// the type of a binary operator's result, which mirrors the operators the interpreter supports
+    /// anything can be done to a value of an open type, as a type parameter could be anything
+    fn binary(&mut self, op: &TokenType, lhs: &Type, rhs: &Type, line: usize) -> Type {
+        let comparison = matches!(op, TokenType::Eq | TokenType::Neq | TokenType::Lt | TokenType::Gt |
+            TokenType::Lte | TokenType::Gte | TokenType::And | TokenType::Or | TokenType::Xor);
+        if self.is_open(lhs) || self.is_open(rhs) {
+            return match comparison {
+                true => Type::Bool,
+                false if !self.is_open(lhs) => lhs.clone(),
+                false => rhs.clone(),
+            };
+        }
+        let result = match (op, self.shallow(lhs), self.shallow(rhs)) {
+            (TokenType::Plus | TokenType::Minus | TokenType::Star | TokenType::Slash | TokenType::Mod |
+                TokenType::BwOr | TokenType::BwAnd | TokenType::BwXor | TokenType::ShiftLeft | TokenType::ShiftRight,
+                Type::Int, Type::Int) => Some(Type::Int),
+            (TokenType::Plus | TokenType::Minus | TokenType::Star | TokenType::Slash | TokenType::Mod,
+                Type::Float, Type::Float) => Some(Type::Float),
+            (TokenType::Plus, Type::String, Type::String) => Some(Type::String),
+            (TokenType::And | TokenType::Or | TokenType::Xor, Type::Bool, Type::Bool) => Some(Type::Bool),
+            (TokenType::Eq | TokenType::Neq, _, _) if self.unify(lhs, rhs) || self.unify(rhs, lhs) => Some(Type::Bool),
+            (TokenType::Lt | TokenType::Gt | TokenType::Lte | TokenType::Gte, a, b)
+                if a == b && matches!(a, Type::Int | Type::Float | Type::String) => Some(Type::Bool),
+            _ => None,
+        };
+        result.unwrap_or_else(|| {
+            let message = format!("cannot apply '{op}' to {} and {}", self.display(lhs), self.display(rhs));
+            self.error(line, message);
+            match comparison {
+                true => Type::Bool,
+                false => Type::Unknown,
+            }
+        })
+    }
+
+    /// the type of a call to callee with arguments of types args
+    fn call(&mut self, callee: &Tree, args: &[Type], line: usize) -> Type {
+        match &callee.value {
+            TreeType::Leaf(TokenType::Identifier(name)) if !self.is_variable(*name) => {
+                if let Some(&function) = self.functions.get(name) {
+                    let (params, ret) = self.signature(function);
+                    let param_names: Vec<Symbol> = function.params[1].params.iter().map(|x| self::name(&x.params[0])).collect();
+                    let function = format!("'{name}'");
+                    self.arguments(&function, &params, &param_names, args, line);
+                    return self.resolve(&ret);
+                }
+                if let Some(&declaration) = self.structs.get(name) {
+                    return self.construct(declaration, args, line);
+                }
+                // a builtin or native, which are checked when they are called
+                Type::Unknown
+            },
+            TreeType::Member if self.is_enum(&callee.params[0]) => {
+                self.variant(&callee.params[0], self::name(&callee.params[1]), args, line)
+            },
+            TreeType::Member if self.is_module(&callee.params[0]) => Type::Unknown,
+            _ => {
+                let found = self.expression(callee);
+                match self.shallow(&found) {
+                    Type::Function(params, ret) => {
+                        let function = match &callee.value {
+                            TreeType::Leaf(TokenType::Identifier(name)) => format!("'{name}'"),
+                            _ => String::from("function"),
+                        };
+                        self.arguments(&function, &params, &[], args, line);
+                        self.resolve(&ret)
+                    },
+                    _ if self.is_open(&found) => Type::Unknown,
+                    _ => {
+                        let message = format!("cannot call {}", self.display(&found));
+                        self.error(line, message);
+                        Type::Unknown
+                    },
+                }
+            },
+        }
+    }
+
+    /// checks the arguments of a call against the types of the parameters they are passed to,
+    /// which are referred to by name if there are names for them
+    fn arguments(&mut self, function: &str, params: &[Type], names: &[Symbol], args: &[Type], line: usize) {
+        if params.len() != args.len() {
+            self.error(line, format!("{function} takes {} arguments but {} were given", params.len(), args.len()));
+            return;
+        }
+        for (i, (param, arg)) in params.iter().zip(args).enumerate() {
+            let param_name = match names.get(i) {
+                Some(param_name) => format!("parameter '{param_name}'"),
+                None => format!("argument {}", i + 1),
+            };
+            self.expect(param, arg, line, |x, y| format!("expected {x} for {param_name} of {function}, found {y}"));
+        }
+    }
+
+    /// the type of a value of the struct that declaration declares, whose type arguments are
+    /// inferred from the types of its fields
+    fn construct(&mut self, declaration: &Tree, args: &[Type], line: usize) -> Type {
+        let struct_name = name(&declaration.params[0]);
+        let fields = &declaration.params[2..];
+        if fields.len() != args.len() {
+            self.error(line, format!("'{struct_name}' has {} fields but {} were given", fields.len(), args.len()));
+        }
+        let type_params: Vec<Symbol> = declaration.params[1].params.iter().map(name).collect();
+        let type_args: HashMap<Symbol, Type> = type_params.iter().map(|x| (*x, self.var())).collect();
+        let outer = std::mem::replace(&mut self.generics, type_params.clone());
+        let field_types: Vec<Type> = fields.iter().map(|x| self.type_of(&x.params[1])).collect();
+        self.generics = outer;
+        for ((field, field_type), arg) in fields.iter().zip(field_types).zip(args) {
+            let field_type = self.substitute(&field_type, &type_args);
+            let field_name = name(&field.params[0]);
+            self.expect(&field_type, arg, line, |x, y| format!("expected {x} for field '{field_name}' of '{struct_name}', found {y}"));
+        }
+        self.resolve(&Type::Named(struct_name, type_params.iter().map(|x| type_args[x].clone()).collect()))
+    }
+
+    /// the type of the field called field of a value of type t
+    fn field(&mut self, t: &Type, field: Symbol, line: usize) -> Type {
+        let (struct_name, type_args) = match self.shallow(t) {
+            Type::Named(struct_name, type_args) if self.structs.contains_key(&struct_name) => (struct_name, type_args),
+            // the error a catch binds, unless the file declares its own Error
+            Type::Named(struct_name, _) if struct_name.as_str() == ERROR => return match field.as_str() {
+                "message" => Type::String,
+                "line" => Type::Int,
+                "trace" => Type::Array,
+                _ => {
+                    self.error(line, format!("{ERROR} has no field '{field}'"));
+                    Type::Unknown
+                },
+            },
+            _ if self.is_open(t) => return Type::Unknown,
+            _ => {
+                let message = format!("{} has no field '{field}'", self.display(t));
+                self.error(line, message);
+                return Type::Unknown;
+            },
+        };
+        let declaration = self.structs[&struct_name];
+        let found = match declaration.params[2..].iter().find(|x| name(&x.params[0]) == field) {
+            Some(found) => found,
+            None => {
+                self.error(line, format!("{struct_name} has no field '{field}'"));
+                return Type::Unknown;
+            },
+        };
+        let type_params: Vec<Symbol> = declaration.params[1].params.iter().map(name).collect();
+        let outer = std::mem::replace(&mut self.generics, type_params.clone());
+        let field_type = self.type_of(&found.params[1]);
+        self.generics = outer;
+        self.substitute(&field_type, &type_params.into_iter().zip(type_args).collect())
+    }
+
+    /// the type of a variant of the enum that tree names, checking the types of its fields
+    fn variant(&mut self, tree: &Tree, variant: Symbol, args: &[Type], line: usize) -> Type {
+        let enum_name = name(tree);
+        let fields = match self.variant_fields(tree, variant) {
+            Some(fields) => fields,
+            None => {
+                self.error(line, format!("'{enum_name}' has no variant '{variant}'"));
+                return Type::Named(enum_name, Vec::new());
+            },
+        };
+        if fields.len() != args.len() {
+            self.error(line, format!("'{enum_name}.{variant}' has {} fields but {} were given", fields.len(), args.len()));
+        }
+        for (field, arg) in fields.iter().zip(args) {
+            self.expect(field, arg, line, |x, y| format!("expected {x} for field of '{enum_name}.{variant}', found {y}"));
+        }
+        Type::Named(enum_name, Vec::new())
+    }
+}
+
+fn name(leaf: &Tree) -> Symbol {
+    match &leaf.value {
+        TreeType::Leaf(TokenType::Identifier(name)) => *name,
+        other => panic!("({}) expected identifier, found {other:?}", leaf.line),
+    }
+}
+
+/// whether a body never reaches its end, because its last statement always leaves it
+fn diverges(body: &Tree) -> bool {
+    body.params.last().is_some_and(|x| matches!(x.value, TreeType::Return | TreeType::Break | TreeType::Continue | TreeType::Throw))
+}
+
+/// whether tree has a break that might leave the loop it is in, nested if it is in a loop of its own
+/// a labelled break might be for any loop, so it always might
+fn breaks(tree: &Tree, nested: bool) -> bool {
+    match tree.value {
+        TreeType::Break => !nested || !tree.params.is_empty(),
+        TreeType::While | TreeType::For | TreeType::Forever => tree.params.iter().any(|x| breaks(x, true)),
+        TreeType::Lambda => false,
+        _ => tree.params.iter().any(|x| breaks(x, nested)),
+    }
+}
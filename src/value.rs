@@ -0,0 +1,260 @@
+//! values that scripts work with at runtime, and what the operators do to them
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::interpreter::Local;
+use crate::lexer::TokenType;
+use crate::parser::{Tree, TreeType};
+use crate::symbol::Symbol;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Void, // the result of calling a function that returns nothing
+    None, // the value of an optional that has no value
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    Array(Vec<Value>),
+    Enum{ name: Symbol, variant: Symbol, fields: Vec<Value> }, // a variant of the enum called name
+    Function(Function),
+    Struct{ name: Symbol, fields: Vec<(Symbol, Value)> }, // fields in the order they were declared
+}
+
+/// a function as a value, either a declared function or a lambda along with the locals it captured
+#[derive(Clone)]
+pub struct Function {
+    pub(crate) tree: Rc<Tree>, // a Function or Lambda tree
+    pub(crate) module: usize, // the module it was declared in, whose globals it can see
+    pub(crate) captures: Rc<HashMap<Symbol, Local>>, // shared with the scope they were captured from
+    pub(crate) generics: Rc<[Symbol]>, // type parameters that its types can refer to
+}
+
+impl Function {
+    /// the name of a declared function, None for a lambda
+    pub fn name(&self) -> Option<Symbol> {
+        match (&self.tree.value, self.tree.params.first().map(|x| &x.value)) {
+            (TreeType::Function, Some(TreeType::Leaf(TokenType::Identifier(name)))) => Some(*name),
+            _ => None,
+        }
+    }
+}
+
+// functions are only equal to themselves, a lambda is a new function every time it is evaluated
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.tree, &other.tree)
+            && (Rc::ptr_eq(&self.captures, &other.captures) || self.captures.is_empty() && other.captures.is_empty())
+    }
+}
+
+impl std::fmt::Debug for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Value::Function(self.clone()))
+    }
+}
+
+impl Value {
+    /// the name of the type of this value as it is written in source
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Void => "void",
+            Value::None => "none",
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Bool(_) => "bool",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Enum{ name, .. } => name.as_str(),
+            Value::Function(_) => "fn",
+            Value::Struct{ name, .. } => name.as_str(),
+        }
+    }
+
+    /// the type leaf token that names the type of this value
+    pub fn type_token(&self) -> TokenType {
+        match self {
+            Value::Void => TokenType::Void,
+            // none could be any optional
+            Value::None => TokenType::Question,
+            Value::Int(_) => TokenType::Int,
+            Value::Float(_) => TokenType::Float,
+            Value::Bool(_) => TokenType::Bool,
+            Value::String(_) => TokenType::String,
+            // there is no syntax for array types yet, so they are named like a struct would be
+            Value::Array(_) => TokenType::Identifier(Symbol::intern("array")),
+            Value::Enum{ name, .. } => TokenType::Identifier(*name),
+            Value::Function(_) => TokenType::Function,
+            Value::Struct{ name, .. } => TokenType::Identifier(*name),
+        }
+    }
+
+    /// whether this value can be stored somewhere declared with type_name, a type leaf's token
+    /// names that are not built in accept anything but a value of some other enum or struct for now
+    /// optionals accept any value, since values do not know the type they are optional of
+    pub fn has_type(&self, type_name: &TokenType) -> bool {
+        if let Value::None = self {
+            return matches!(type_name, TokenType::Question);
+        }
+        match type_name {
+            TokenType::Int => matches!(self, Value::Int(_)),
+            TokenType::Float => matches!(self, Value::Float(_)),
+            TokenType::Bool => matches!(self, Value::Bool(_)),
+            TokenType::String => matches!(self, Value::String(_)),
+            TokenType::Void => matches!(self, Value::Void),
+            TokenType::Function => matches!(self, Value::Function(_)),
+            TokenType::Identifier(type_name) => match self {
+                Value::Enum{ name, .. } | Value::Struct{ name, .. } => name == type_name,
+                _ => true,
+            },
+            _ => true,
+        }
+    }
+}
+
+impl From<i64> for Value {
+    fn from(val: i64) -> Self {
+        Value::Int(val)
+    }
+}
+impl From<f64> for Value {
+    fn from(val: f64) -> Self {
+        Value::Float(val)
+    }
+}
+impl From<bool> for Value {
+    fn from(val: bool) -> Self {
+        Value::Bool(val)
+    }
+}
+impl From<String> for Value {
+    fn from(val: String) -> Self {
+        Value::String(val)
+    }
+}
+impl From<&str> for Value {
+    fn from(val: &str) -> Self {
+        Value::String(val.to_string())
+    }
+}
+impl From<()> for Value {
+    fn from(_: ()) -> Self {
+        Value::Void
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Void => write!(f, "void"),
+            Value::None => write!(f, "none"),
+            Value::Int(val) => write!(f, "{val}"),
+            Value::Float(val) => write!(f, "{val:?}"), // debug so that whole numbers keep their '.0'
+            Value::Bool(val) => write!(f, "{val}"),
+            Value::String(val) => write!(f, "{val}"),
+            Value::Array(vals) => {
+                let vals: Vec<String> = vals.iter().map(Value::to_string).collect();
+                write!(f, "[{}]", vals.join(", "))
+            },
+            Value::Enum{ name, variant, fields } => {
+                write!(f, "{name}.{variant}")?;
+                if !fields.is_empty() {
+                    let fields: Vec<String> = fields.iter().map(Value::to_string).collect();
+                    write!(f, "({})", fields.join(", "))?;
+                }
+                Ok(())
+            },
+            Value::Function(function) => match function.name() {
+                Some(name) => write!(f, "<fn {name}>"),
+                None => write!(f, "<lambda>"),
+            },
+            Value::Struct{ name, fields } => {
+                write!(f, "{name}(")?;
+                for (i, (field, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{field}: {value}")?;
+                }
+                write!(f, ")")
+            },
+        }
+    }
+}
+
+/// the error for int arithmetic whose result does not fit in an int
+pub const OVERFLOW: &str = "integer overflow";
+
+/// applies a binary operator other than '&&' and '||', which the interpreter short circuits
+/// the error is a message without a line, for the caller to attach one to
+///
+/// int arithmetic is checked, a result that does not fit in an int is an error rather than wrapping
+/// around (the wrapping_ and saturating_ builtins do that instead). '/' rounds towards zero and '%'
+/// has the sign of its left operand, so that `(a / b) * b + a % b == a`. the one exception is that
+/// the minimum int divided by -1 overflows, while its remainder is simply 0. shifts lose whatever bits
+/// are shifted out, '>>' keeps the sign, and shifting by less than 0 or more than 63 is an error
+pub fn binary_op(op: &TokenType, lhs: Value, rhs: Value) -> Result<Value, String> {
+    let mismatch = |lhs: &Value, rhs: &Value| {
+        format!("cannot apply '{op}' to {} and {}", lhs.type_name(), rhs.type_name())
+    };
+    Ok(match (op, lhs, rhs) {
+        (TokenType::Plus, Value::Int(a), Value::Int(b)) => Value::Int(a.checked_add(b).ok_or(OVERFLOW)?),
+        (TokenType::Minus, Value::Int(a), Value::Int(b)) => Value::Int(a.checked_sub(b).ok_or(OVERFLOW)?),
+        (TokenType::Star, Value::Int(a), Value::Int(b)) => Value::Int(a.checked_mul(b).ok_or(OVERFLOW)?),
+        (TokenType::Slash | TokenType::Mod, Value::Int(_), Value::Int(0)) => return Err(String::from("division by zero")),
+        (TokenType::Slash, Value::Int(a), Value::Int(b)) => Value::Int(a.checked_div(b).ok_or(OVERFLOW)?),
+        // only the minimum int % -1 wraps, and its remainder is 0 all the same
+        (TokenType::Mod, Value::Int(a), Value::Int(b)) => Value::Int(a.wrapping_rem(b)),
+        (TokenType::Plus, Value::Float(a), Value::Float(b)) => Value::Float(a + b),
+        (TokenType::Minus, Value::Float(a), Value::Float(b)) => Value::Float(a - b),
+        (TokenType::Star, Value::Float(a), Value::Float(b)) => Value::Float(a * b),
+        (TokenType::Slash, Value::Float(a), Value::Float(b)) => Value::Float(a / b),
+        (TokenType::Mod, Value::Float(a), Value::Float(b)) => Value::Float(a % b),
+        (TokenType::Plus, Value::String(a), Value::String(b)) => Value::String(a + &b),
+        (TokenType::BwOr, Value::Int(a), Value::Int(b)) => Value::Int(a | b),
+        (TokenType::BwAnd, Value::Int(a), Value::Int(b)) => Value::Int(a & b),
+        (TokenType::BwXor, Value::Int(a), Value::Int(b)) => Value::Int(a ^ b),
+        (TokenType::ShiftLeft | TokenType::ShiftRight, Value::Int(a), Value::Int(b)) => {
+            let shifted = match (op, u32::try_from(b)) {
+                (TokenType::ShiftLeft, Ok(b)) => a.checked_shl(b),
+                (_, Ok(b)) => a.checked_shr(b),
+                (_, Err(_)) => None,
+            };
+            Value::Int(shifted.ok_or_else(|| format!("cannot shift by {b}, shifts must be from 0 to 63"))?)
+        },
+        (TokenType::Xor, Value::Bool(a), Value::Bool(b)) => Value::Bool(a ^ b),
+        // an optional can be compared with none whatever it is an optional of
+        (TokenType::Eq, a, b) if a.type_name() == b.type_name() || a == Value::None || b == Value::None => Value::Bool(a == b),
+        (TokenType::Neq, a, b) if a.type_name() == b.type_name() || a == Value::None || b == Value::None => Value::Bool(a != b),
+        (TokenType::Lt | TokenType::Gt | TokenType::Lte | TokenType::Gte, a, b) => {
+            let ordering = match (&a, &b) {
+                (Value::Int(a), Value::Int(b)) => a.partial_cmp(b),
+                (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+                (Value::String(a), Value::String(b)) => a.partial_cmp(b),
+                _ => return Err(mismatch(&a, &b)),
+            };
+            Value::Bool(match ordering {
+                Some(ordering) => match op {
+                    TokenType::Lt => ordering.is_lt(),
+                    TokenType::Gt => ordering.is_gt(),
+                    TokenType::Lte => ordering.is_le(),
+                    _ => ordering.is_ge(),
+                },
+                None => false, // NaN is not ordered against anything
+            })
+        },
+        (_, a, b) => return Err(mismatch(&a, &b)),
+    })
+}
+
+/// applies a unary operator, the error is a message without a line
+pub fn unary_op(op: &TokenType, operand: Value) -> Result<Value, String> {
+    Ok(match (op, operand) {
+        (TokenType::Minus, Value::Int(a)) => Value::Int(a.checked_neg().ok_or(OVERFLOW)?),
+        (TokenType::Minus, Value::Float(a)) => Value::Float(-a),
+        (TokenType::Not, Value::Bool(a)) => Value::Bool(!a),
+        (TokenType::BwNot, Value::Int(a)) => Value::Int(!a),
+        (_, a) => return Err(format!("cannot apply '{op}' to {}", a.type_name())),
+    })
+}
@@ -0,0 +1,150 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use interpreter::engine::{Engine, Error};
+use interpreter::symbol::Symbol;
+use interpreter::value::Value;
+
+const SCRIPT: &str = "
+var calls: int = 0;
+
+fn add(a: int, b: int) -> int {
+  calls = calls + 1;
+  return a + b;
+}
+
+fn scaled(x: float) -> float {
+  return x * scale;
+}
+
+fn stamp() -> int {
+  return now() + 1;
+}
+";
+
+#[test]
+fn call_script_functions() {
+    let mut engine = Engine::new();
+    engine.run(SCRIPT).unwrap();
+    assert_eq!(engine.call("add", vec![Value::Int(2), Value::Int(3)]).unwrap(), Value::Int(5));
+    assert_eq!(engine.call("add", vec![40.into(), 2.into()]).unwrap(), Value::Int(42));
+    assert_eq!(engine.global("calls"), Some(&Value::Int(2)));
+    // builtins can be called the same way
+    assert_eq!(engine.call("max", vec![1.into(), 7.into()]).unwrap(), Value::Int(7));
+}
+
+#[test]
+fn globals_and_native_functions() {
+    let mut engine = Engine::new();
+    engine.run(SCRIPT).unwrap();
+    engine.set_global("scale", 2.5).unwrap();
+    assert_eq!(engine.call("scaled", vec![Value::Float(4.0)]).unwrap(), Value::Float(10.0));
+    assert!(engine.set_global("calls", "not an int").is_err());
+
+    let ticks = Rc::new(Cell::new(0));
+    let counter = ticks.clone();
+    engine.register_fn("now", move |args| {
+        if !args.is_empty() {
+            return Err(String::from("now takes no arguments"));
+        }
+        counter.set(counter.get() + 100);
+        Ok(Value::Int(counter.get()))
+    });
+    assert_eq!(engine.call("stamp", Vec::new()).unwrap(), Value::Int(101));
+    assert_eq!(ticks.get(), 100);
+}
+
+#[test]
+fn structured_errors() {
+    let mut engine = Engine::new();
+    match engine.run("fn main( {}") {
+        Err(Error::Syntax(error)) => assert_eq!(error.line, Some(1)),
+        other => panic!("expected a syntax error, got {other:?}"),
+    }
+    match engine.run("fn twice(x: int) -> int {\n  return x * 2;\n}\nconst y: int = twice(\"2\");") {
+        Err(Error::Type(error)) => {
+            assert_eq!(error.line, Some(4));
+            assert_eq!(error.message, "expected int for parameter 'x' of 'twice', found string");
+        },
+        other => panic!("expected a type error, got {other:?}"),
+    }
+    engine.run("fn fail(x: int) -> int {\n  return x / 0;\n}\nfn leave() {\n  exit(7);\n}").unwrap();
+    match engine.call("fail", vec![Value::Int(1)]) {
+        Err(Error::Runtime(error)) => {
+            assert_eq!(error.line, Some(2));
+            assert_eq!(error.message, "division by zero");
+            // called by the engine, so there is no line it was called at
+            assert_eq!(error.trace.len(), 1);
+            assert_eq!(error.trace[0].function, "'fail'");
+            assert_eq!(error.trace[0].line, None);
+            assert_eq!(error.trace[0].args, ["x: 1"]);
+        },
+        other => panic!("expected a runtime error, got {other:?}"),
+    }
+    assert!(matches!(engine.call("fail", vec![Value::Bool(true)]), Err(Error::Runtime(_))));
+    assert!(matches!(engine.call("missing", Vec::new()), Err(Error::Runtime(_))));
+    assert!(matches!(engine.call("leave", Vec::new()), Err(Error::Exit(7))));
+}
+
+#[test]
+fn rendered_runtime_errors() {
+    let source = "fn half(s: string, n: int) -> int {\n  return n / 0;\n}\nfn main() {\n  half(\"x\", 4);\n}";
+    let mut engine = Engine::new();
+    engine.run(source).unwrap();
+    let Err(Error::Runtime(error)) = engine.call("main", Vec::new()) else {
+        panic!("expected a runtime error");
+    };
+    assert_eq!(error.render("half.in", source), "\
+error: division by zero
+  --> half.in:2
+  |
+2 |   return n / 0;
+  |
+note: in 'half'(s: \"x\", n: 4)
+  --> half.in:5
+  |
+5 |   half(\"x\", 4);
+  |
+note: in 'main'()
+");
+}
+
+#[test]
+fn imports_resolve_relative_to_the_file() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("files");
+    let mut engine = Engine::new();
+    engine.run_file(dir.join("modules.in")).unwrap();
+    assert!(engine.has_function("main"));
+    // only the file that was run is callable by name, not the modules it imports
+    assert!(!engine.has_function("area"));
+
+    let mut engine = Engine::new();
+    match engine.run_file(dir.join("cycle.in")) {
+        Err(Error::Runtime(error)) => assert!(error.message.starts_with("import cycle: "), "{error}"),
+        other => panic!("expected an import cycle, found {other:?}"),
+    }
+}
+
+#[test]
+fn engines_side_by_side() {
+    let mut first = Engine::new();
+    let mut second = Engine::new();
+    first.run("fn id() -> int {\n  return 1;\n}").unwrap();
+    second.run("fn id() -> int {\n  return 2;\n}").unwrap();
+    assert_eq!(first.call("id", Vec::new()).unwrap(), Value::Int(1));
+    assert_eq!(second.call("id", Vec::new()).unwrap(), Value::Int(2));
+}
+
+#[test]
+fn looking_up_missing_names() {
+    let mut engine = Engine::new();
+    engine.run(SCRIPT).unwrap();
+    assert!(!engine.has_function("engine_test_missing"));
+    assert_eq!(engine.global("engine_test_missing"), None);
+    match engine.call("engine_test_missing", Vec::new()) {
+        Err(Error::Runtime(error)) => assert_eq!(error.message, "unknown function 'engine_test_missing'"),
+        other => panic!("expected an unknown function, found {other:?}"),
+    }
+    // none of which is kept around as a name
+    assert_eq!(Symbol::lookup("engine_test_missing"), None);
+}
@@ -0,0 +1,149 @@
+//! snapshot tests over the sample programs in files/
+//!
+//! for every files/<name>.in the tokens, tree, what running it prints and diagnostics are written out
+//! as text and compared against files/<name>.expected. run with BLESS=1 to write the current output
+//! as the new snapshots
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use interpreter::builtins::Io;
+use interpreter::check;
+use interpreter::diagnostic;
+use interpreter::interpreter as runtime;
+use interpreter::lexer;
+use interpreter::optimize;
+use interpreter::parser;
+use interpreter::types;
+
+/// output that can still be read after the interpreter that wrote to it is gone
+#[derive(Clone, Default)]
+struct SharedOutput(Arc<Mutex<Vec<u8>>>);
+
+impl std::io::Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// everything that is snapshotted for one input, in the order it is written
+/// path is relative to the working directory, so that it is the same wherever the repo is
+fn snapshot(input: &str, path: &Path) -> String {
+    let mut output = String::new();
+    let mut diagnostics = Vec::new();
+
+    output.push_str("== tokens ==\n");
+    let tokens = match diagnostic::catch(|| lexer::lex(input)) {
+        Ok(tokens) => tokens,
+        Err(error) => {
+            diagnostics.push(error);
+            Vec::new()
+        },
+    };
+    for token in &tokens {
+        writeln!(output, "{}:{}..{} {:?}", token.line, token.start, token.end, token.value).unwrap();
+    }
+
+    output.push_str("\n== tree ==\n");
+    let mut tree = None;
+    if diagnostics.is_empty() {
+        match diagnostic::catch(|| parser::parse(tokens)) {
+            Ok(mut parsed) => {
+                write!(output, "{parsed:?}").unwrap();
+                // a file with type errors is not optimized, and one that cannot be is not run
+                let mut errors = types::check(&parsed);
+                let warnings = check::check(&parsed);
+                if errors.is_empty() {
+                    errors = optimize::optimize(&mut parsed);
+                }
+                let run = errors.is_empty();
+                diagnostics.extend(errors);
+                diagnostics.extend(warnings);
+                if run {
+                    tree = Some(parsed);
+                }
+            },
+            Err(error) => diagnostics.push(error),
+        }
+    }
+
+    // scripts are run with nothing on stdin
+    output.push_str("\n== stdout ==\n");
+    if let Some(tree) = tree {
+        let stdout = SharedOutput::default();
+        let io = Io{
+            input: Box::new(std::io::empty()),
+            output: Box::new(stdout.clone()),
+        };
+        let result = runtime::run(&tree, path, io);
+        output.push_str(&String::from_utf8_lossy(&stdout.0.lock().unwrap()));
+        match result {
+            Ok(0) => {},
+            Ok(code) => writeln!(output, "\n(exit code {code})").unwrap(),
+            Err(error) => diagnostics.push(error),
+        }
+    }
+
+    output.push_str("\n== diagnostics ==\n");
+    for diagnostic in &diagnostics {
+        output.push_str(&diagnostic.render(&path.display().to_string(), input));
+    }
+    output
+}
+
+fn inputs() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("files");
+    let mut inputs: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .unwrap_or_else(|_| panic!("could not read {}", dir.display()))
+        .map(|x| x.unwrap().path())
+        .filter(|x| x.extension().is_some_and(|x| x == "in"))
+        .collect();
+    inputs.sort();
+    inputs
+}
+
+/// the first line at which expected and actual differ, for the failure message
+fn first_difference(expected: &str, actual: &str) -> String {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    for line in 1.. {
+        match (expected_lines.next(), actual_lines.next()) {
+            (None, None) => break,
+            (expected, actual) if expected != actual => {
+                return format!("line {line}:\n  expected: {}\n  actual:   {}",
+                    expected.unwrap_or("<end of file>"), actual.unwrap_or("<end of file>"));
+            },
+            _ => {},
+        }
+    }
+    String::from("trailing newline differs")
+}
+
+#[test]
+fn golden() {
+    let bless = std::env::var_os("BLESS").is_some_and(|x| x != "0");
+    let inputs = inputs();
+    assert!(!inputs.is_empty(), "no .in files found");
+    let mut failures = Vec::new();
+    for input_path in inputs {
+        let input = std::fs::read_to_string(&input_path).unwrap();
+        let relative = input_path.strip_prefix(env!("CARGO_MANIFEST_DIR")).unwrap();
+        let actual = snapshot(&input, relative);
+        let expected_path = input_path.with_extension("expected");
+        if bless {
+            std::fs::write(&expected_path, &actual).unwrap();
+            continue;
+        }
+        match std::fs::read_to_string(&expected_path) {
+            Ok(expected) if expected == actual => {},
+            Ok(expected) => failures.push(format!("{} does not match, {}",
+                expected_path.display(), first_difference(&expected, &actual))),
+            Err(_) => failures.push(format!("{} is missing", expected_path.display())),
+        }
+    }
+    assert!(failures.is_empty(), "{}\n\nrerun with BLESS=1 to update the snapshots", failures.join("\n"));
+}
@@ -0,0 +1,120 @@
+use interpreter::diagnostic;
+use interpreter::incremental::{Parsed, TextEdit};
+use interpreter::parser::Tree;
+
+const SOURCE: &str = "const a: int = 12;
+// comment
+fn main() {
+  var x: int = 0;
+  var s: string = \"hello // world\";
+  while (x == a) {
+    x = x + 1; // trailing
+    my_func(x);
+  }
+  if (x > 2) { x = 2; } else { return; }
+}
+
+fn my_func(x: int) -> int {
+  var z: int = x * x;
+  return z;
+}
+
+fn last() {}
+";
+
+fn tree_text(tree: &Tree) -> String {
+    let mut text = format!("{:?}@{}-{}:{}..{}(", tree.value, tree.line, tree.end_line, tree.start, tree.end);
+    if let interpreter::parser::TreeType::Leaf(token) = &tree.value {
+        text.push_str(&format!("{token:?}"));
+    }
+    for param in &tree.params {
+        text.push_str(&tree_text(param));
+    }
+    text.push(')');
+    text
+}
+
+/// applies edit incrementally and checks the result against parsing the new text from scratch
+/// returns whether the edit was applied
+fn check_edit(parsed: &mut Parsed, edit: TextEdit) -> bool {
+    let mut text = parsed.text.clone();
+    text.replace_range(edit.range.clone(), &edit.text);
+    let full = diagnostic::catch(|| Parsed::new(text.clone()));
+    let incremental = diagnostic::catch(|| parsed.edit(&edit));
+    match full {
+        Ok(full) => {
+            assert!(incremental.is_ok(), "incremental edit failed where a full parse did not: {edit:?}");
+            assert_eq!(parsed.text, full.text);
+            assert_eq!(parsed.tokens, full.tokens, "tokens differ after {edit:?}");
+            assert_eq!(tree_text(&parsed.tree), tree_text(&full.tree), "trees differ after {edit:?}");
+            true
+        },
+        Err(_) => {
+            assert!(incremental.is_err(), "incremental edit succeeded where a full parse did not: {edit:?}");
+            false
+        },
+    }
+}
+
+fn edit(start: usize, end: usize, text: &str) -> TextEdit {
+    TextEdit{
+        range: start..end,
+        text: text.to_string(),
+    }
+}
+
+#[test]
+fn single_edits() {
+    let find = |pattern: &str| SOURCE.find(pattern).unwrap();
+    let edits = [
+        edit(0, 0, "const b: int = 1;\n"), // insert at the start
+        edit(SOURCE.len(), SOURCE.len(), "fn more() {}\n"), // insert at the end
+        edit(find("12"), find("12") + 2, "1 + 2"), // inside of a top level const
+        edit(find("x + 1"), find("x + 1") + 1, "xyz"), // rename inside of a function
+        edit(find("== a"), find("== a") + 2, "="), // shrink a token, breaks the parse
+        edit(find("= 0") + 1, find("= 0") + 1, "="), // grow a token, breaks the parse
+        edit(find("// comment"), find("// comment") + 2, ""), // uncomment, breaks the parse
+        edit(find("// comment") + 10, find("// comment") + 10, "\nconst c: int = 3;"),
+        edit(find("\"hello"), find("\"hello") + 1, ""), // an unterminated string
+        edit(find("fn my_func"), find("fn my_func"), "// "), // comment out a line
+        edit(find("  var z"), find("  var z"), "\n\n\n"), // move everything after down
+        edit(find("}\n\nfn my_func"), find("}\n\nfn my_func") + 1, ""), // merge two functions
+        edit(find("fn last"), SOURCE.len(), ""), // delete a whole function
+    ];
+    for edit in edits {
+        let mut parsed = Parsed::new(SOURCE.to_string());
+        check_edit(&mut parsed, edit);
+    }
+    // the lexer looks two chars past an int to tell it apart from a float, so a range can turn the
+    // int before it into a float without that int touching the edit
+    let source = "fn main() {\n  for i in 0..10 {}\n}\n";
+    let mut parsed = Parsed::new(source.to_string());
+    let start = source.find(".10").unwrap();
+    assert!(check_edit(&mut parsed, edit(start, start + 3, "1")));
+}
+
+#[test]
+fn random_edit_sequences() {
+    let snippets = ["", "x", " ", "\n", "+ 1", "}", "{", "fn f() {}\n", "//", "\"", "var q: int = 3;",
+        "==", "=", "-", ">", "(", ")", ";", "1.5", "if (x) { }", "const k: int = 2;\n",
+        ".", "..", "..="];
+    // small linear congruential generator so the sequence is the same every run
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut random = |max: usize| {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (state >> 33) as usize % max
+    };
+    let mut applied = 0;
+    for _ in 0..20 {
+        let mut parsed = Parsed::new(SOURCE.to_string());
+        for _ in 0..100 {
+            let start = random(parsed.text.len() + 1);
+            let end = (start + random(8)).min(parsed.text.len());
+            let text = snippets[random(snippets.len())];
+            if check_edit(&mut parsed, edit(start, end, text)) {
+                applied += 1;
+            }
+        }
+    }
+    assert!(applied > 100, "too few edits parsed to be a useful test: {applied}");
+}
@@ -0,0 +1,40 @@
+//! equality of tokens, which compares the values of literals rather than only their kinds
+
+use std::collections::HashSet;
+
+use interpreter::lexer::{self, TokenType};
+use interpreter::symbol::Symbol;
+
+#[test]
+fn literals_compare_their_values() {
+    assert_eq!(TokenType::<String>::IntLit(1), TokenType::IntLit(1));
+    assert_ne!(TokenType::<String>::IntLit(1), TokenType::IntLit(2));
+    assert_ne!(TokenType::<String>::BoolLit(true), TokenType::BoolLit(false));
+    assert_ne!(TokenType::StringLit("a".to_string()), TokenType::StringLit("b".to_string()));
+    assert_ne!(TokenType::<String>::Identifier(Symbol::intern("a")), TokenType::Identifier(Symbol::intern("b")));
+    assert_ne!(TokenType::<String>::IntLit(1), TokenType::FloatLit(1.0));
+    assert_eq!(TokenType::<String>::Plus, TokenType::Plus);
+    assert_ne!(TokenType::<String>::Plus, TokenType::Minus);
+    // lexing different numbers gives different tokens
+    let values = |input: &str| lexer::lex(input).into_iter().map(|x| x.value).collect::<Vec<_>>();
+    assert_ne!(values("1 + 1"), values("1 + 2"));
+}
+
+#[test]
+fn floats_compare_their_bits() {
+    assert_eq!(TokenType::<String>::FloatLit(1.5), TokenType::FloatLit(1.5));
+    assert_ne!(TokenType::<String>::FloatLit(0.0), TokenType::FloatLit(-0.0));
+    assert_eq!(TokenType::<String>::FloatLit(f64::NAN), TokenType::FloatLit(f64::NAN));
+    // so equality agrees with hashing
+    let set: HashSet<TokenType> = [0.0, -0.0, f64::NAN, f64::NAN].into_iter().map(TokenType::FloatLit).collect();
+    assert_eq!(set.len(), 3);
+}
+
+#[test]
+fn same_kind_ignores_values() {
+    assert!(TokenType::<String>::IntLit(1).same_kind(&TokenType::IntLit(2)));
+    assert!(TokenType::<String>::FloatLit(0.0).same_kind(&TokenType::FloatLit(f64::NAN)));
+    assert!(TokenType::StringLit("a".to_string()).same_kind(&TokenType::StringLit("b".to_string())));
+    assert!(!TokenType::<String>::IntLit(1).same_kind(&TokenType::FloatLit(1.0)));
+    assert!(!TokenType::<String>::Plus.same_kind(&TokenType::Minus));
+}
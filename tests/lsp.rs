@@ -0,0 +1,169 @@
+//! runs the language server binary and talks to it the way an editor would
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+// the server's own json, rather than pulling in a dependency just for the tests
+#[allow(dead_code)]
+#[path = "../src/bin/lsp/json.rs"]
+mod json;
+
+use json::{Json, object};
+
+const URI: &str = "file:///test.in";
+
+const SOURCE: &str = "const limit: int = 3;
+
+fn double(x: int) -> int {
+  return x * 2;
+}
+
+fn main() {
+  println(double(limit));
+}
+";
+
+struct Client {
+    server: Child,
+    input: ChildStdin,
+    output: BufReader<ChildStdout>,
+    next_id: usize,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut server = Command::new(env!("CARGO_BIN_EXE_lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("could not start the language server");
+        let input = server.stdin.take().unwrap();
+        let output = BufReader::new(server.stdout.take().unwrap());
+        Client{
+            server,
+            input,
+            output,
+            next_id: 1,
+        }
+    }
+
+    fn send(&mut self, message: Json) {
+        let body = message.to_string();
+        write!(self.input, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+        self.input.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Json {
+        let mut length = None;
+        loop {
+            let mut header = String::new();
+            assert!(self.output.read_line(&mut header).unwrap() > 0, "the server closed its output");
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length:") {
+                length = value.trim().parse().ok();
+            }
+        }
+        let mut body = vec![0; length.expect("a message without a Content-Length")];
+        self.output.read_exact(&mut body).unwrap();
+        json::parse(&String::from_utf8(body).unwrap()).unwrap()
+    }
+
+    /// sends a request and returns the result of its response
+    fn request(&mut self, method: &str, params: Json) -> Json {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(object(vec![("jsonrpc", "2.0".into()), ("id", id.into()), ("method", method.into()), ("params", params)]));
+        let response = self.receive();
+        assert_eq!(response.get("id").and_then(Json::as_usize), Some(id));
+        assert!(response.get("error").is_none(), "{method} failed: {response}");
+        response.get("result").cloned().unwrap()
+    }
+
+    fn notify(&mut self, method: &str, params: Json) {
+        self.send(object(vec![("jsonrpc", "2.0".into()), ("method", method.into()), ("params", params)]));
+    }
+
+    /// the messages of the diagnostics published in response to the last notification
+    fn diagnostics(&mut self) -> Vec<String> {
+        let notification = self.receive();
+        assert_eq!(notification.get("method").and_then(Json::as_str), Some("textDocument/publishDiagnostics"));
+        let params = notification.get("params").unwrap();
+        assert_eq!(params.get("uri").and_then(Json::as_str), Some(URI));
+        params.get("diagnostics").and_then(Json::as_array).unwrap().iter()
+            .map(|x| x.get("message").and_then(Json::as_str).unwrap().to_string())
+            .collect()
+    }
+}
+
+fn document() -> Json {
+    object(vec![("uri", URI.into())])
+}
+
+/// params for a request about the position at line and character, both counted from 0
+fn at(line: usize, character: usize) -> Json {
+    object(vec![
+        ("textDocument", document()),
+        ("position", object(vec![("line", line.into()), ("character", character.into())])),
+    ])
+}
+
+fn change(text: &str) -> Json {
+    object(vec![
+        ("textDocument", document()),
+        ("contentChanges", Json::Array(vec![object(vec![("text", text.into())])])),
+    ])
+}
+
+#[test]
+fn editor_session() {
+    let mut client = Client::start();
+    let result = client.request("initialize", object(vec![]));
+    let capabilities = result.get("capabilities").unwrap();
+    assert_eq!(capabilities.get("textDocumentSync").and_then(Json::as_usize), Some(1));
+    client.notify("initialized", object(vec![]));
+
+    client.notify("textDocument/didOpen", object(vec![("textDocument", object(vec![
+        ("uri", URI.into()),
+        ("languageId", "interpreter".into()),
+        ("version", 1.into()),
+        ("text", SOURCE.into()),
+    ]))]));
+    assert_eq!(client.diagnostics(), Vec::<String>::new());
+
+    let symbols = client.request("textDocument/documentSymbol", object(vec![("textDocument", document())]));
+    let names: Vec<&str> = symbols.as_array().unwrap().iter().map(|x| x.get("name").and_then(Json::as_str).unwrap()).collect();
+    assert_eq!(names, ["limit", "double", "main"]);
+
+    // `double` in `println(double(limit));` is declared on line 2
+    let definition = client.request("textDocument/definition", at(7, 11));
+    assert_eq!(definition.get("uri").and_then(Json::as_str), Some(URI));
+    let start = definition.get("range").and_then(|x| x.get("start")).unwrap();
+    assert_eq!(start.get("line").and_then(Json::as_usize), Some(2));
+    assert_eq!(start.get("character").and_then(Json::as_usize), Some(3));
+
+    let hover = client.request("textDocument/hover", at(7, 18));
+    let text = hover.get("contents").and_then(|x| x.get("value")).and_then(Json::as_str).unwrap();
+    assert!(text.contains("const limit: int"), "{text}");
+    assert!(matches!(client.request("textDocument/hover", at(1, 0)), Json::Null));
+
+    // five numbers per token, the first of which is the `const` at the very start
+    let tokens = client.request("textDocument/semanticTokens/full", object(vec![("textDocument", document())]));
+    let data: Vec<usize> = tokens.get("data").and_then(Json::as_array).unwrap().iter().map(|x| x.as_usize().unwrap()).collect();
+    assert_eq!(data.len() % 5, 0);
+    assert_eq!(data[..3], [0, 0, 5]);
+
+    // a change is reanalysed, errors and all
+    client.notify("textDocument/didChange", change("fn main() {\n  var x: int = \"one\";\n}\n"));
+    assert_eq!(client.diagnostics(), ["expected int for 'x', found string"]);
+    client.notify("textDocument/didChange", change("fn main() {\n  var x: int = ;\n}\n"));
+    assert_eq!(client.diagnostics().len(), 1);
+    client.notify("textDocument/didChange", change(SOURCE));
+    assert_eq!(client.diagnostics(), Vec::<String>::new());
+
+    client.request("shutdown", Json::Null);
+    client.notify("exit", Json::Null);
+    assert!(client.server.wait().unwrap().success());
+}
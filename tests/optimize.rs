@@ -0,0 +1,85 @@
+//! the optimization pass, checked by comparing what it makes of a program with a hand optimized
+//! version of the same program
+
+use interpreter::diagnostic::Diagnostic;
+use interpreter::lexer;
+use interpreter::optimize;
+use interpreter::parser::{self, Tree, TreeType};
+use interpreter::types;
+
+/// the tree without any positions, so trees of differently written programs can be compared
+fn shape(tree: &Tree) -> String {
+    let mut text = match &tree.value {
+        TreeType::Leaf(token) => format!("{token:?}"),
+        value => format!("{value:?}"),
+    };
+    text.push('(');
+    for param in &tree.params {
+        text.push_str(&shape(param));
+    }
+    text.push(')');
+    text
+}
+
+fn optimized(input: &str) -> Result<Tree, Vec<Diagnostic>> {
+    let mut tree = parser::parse(lexer::lex(input));
+    let errors = types::check(&tree);
+    assert!(errors.is_empty(), "{input:?} does not type check: {errors:?}");
+    let errors = optimize::optimize(&mut tree);
+    if errors.is_empty() { Ok(tree) } else { Err(errors) }
+}
+
+fn assert_optimizes_to(input: &str, expected: &str) {
+    let tree = optimized(input).unwrap_or_else(|errors| panic!("{input:?} does not optimize: {errors:?}"));
+    assert_eq!(shape(&tree), shape(&parser::parse(lexer::lex(expected))), "input: {input:?}");
+}
+
+#[test]
+fn folds_constant_expressions() {
+    assert_optimizes_to(
+        "const a: int = 12;\nconst b = a * 2 + 1;\nfn f(x: int) -> int {\n  return x + b - (3 * 4);\n}",
+        "const a: int = 12;\nconst b = 25;\nfn f(x: int) -> int {\n  return x + 25 - 12;\n}",
+    );
+    assert_optimizes_to(
+        "fn f(x: bool) -> bool {\n  println(\"a\" + \"b\", 1.5 * 2.0, -(2 - 5), 3 < 4);\n  return false || x && true;\n}",
+        "fn f(x: bool) -> bool {\n  println(\"ab\", 3.0, 3, true);\n  return x && true;\n}",
+    );
+    // only constants are inlined, and not where something else has their name
+    assert_optimizes_to(
+        "const a: int = 1;\nvar v: int = 2;\nfn f(a: int) -> int {\n  return a + v;\n}\nfn g() -> int {\n  const c = a;\n  return c;\n}",
+        "const a: int = 1;\nvar v: int = 2;\nfn f(a: int) -> int {\n  return a + v;\n}\nfn g() -> int {\n  const c = 1;\n  return 1;\n}",
+    );
+    // nor into functions that a top level statement might call before the constant is declared
+    assert_optimizes_to(
+        "fn f() -> int {\n  return a + c;\n}\nconst a = 1;\nprintln(f());\nconst c = 5;\nconst d = c;",
+        "fn f() -> int {\n  return 1 + c;\n}\nconst a = 1;\nprintln(f());\nconst c = 5;\nconst d = 5;",
+    );
+}
+
+#[test]
+fn removes_code_that_never_runs() {
+    assert_optimizes_to(
+        "const debug = false;\nfn f() -> int {\n  if (debug) {\n    println(1);\n  }\n  while (debug) {\n    println(2);\n  }\n  if (!debug) {\n    println(3);\n  } else {\n    println(4);\n  }\n  return 1;\n  println(5);\n}",
+        "const debug = false;\nfn f() -> int {\n  if (true) {\n    println(3);\n  }\n  return 1;\n}",
+    );
+    assert_optimizes_to(
+        "fn f(x: int) {\n  if (false && x > 1) {\n    println(1);\n  } else if (x > 2) {\n    println(2);\n  } else if (true) {\n    println(3);\n  } else {\n    println(4);\n  }\n}",
+        "fn f(x: int) {\n  if (x > 2) {\n    println(2);\n  } else if (true) {\n    println(3);\n  }\n}",
+    );
+    assert_optimizes_to(
+        "fn f() {\n  if (1 > 2) {\n    println(1);\n  } else {\n    println(2);\n  }\n}",
+        "fn f() {\n  if (true) {\n    println(2);\n  }\n}",
+    );
+}
+
+#[test]
+fn overflow_is_a_compile_error() {
+    let errors = optimized("const big: int = 9223372036854775807;\nfn f() -> int {\n  return big + 1;\n}").unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].line, Some(3));
+    assert_eq!(errors[0].message, "integer overflow in constant expression");
+
+    // other errors, and overflow that a try could catch, are left for when the code runs
+    let source = "fn f(x: int) {\n  if (x > 0) {\n    println(1 / 0, 1 << 64);\n  }\n  try {\n    println(9223372036854775807 + 1);\n  } catch error {\n    println(error.message);\n  }\n}";
+    assert_optimizes_to(source, source);
+}
@@ -0,0 +1,233 @@
+//! property tests feeding random and generated programs through the lexer, parser and formatter
+//!
+//! errors are reported by panicking with "(line) message", so any other panic (an unwrap, an index
+//! out of bounds, ...) is a bug. anything that parses must also survive being formatted and parsed
+//! again without its meaning changing, and its concrete syntax tree must give back the exact text
+
+use proptest::prelude::*;
+
+use interpreter::cst;
+use interpreter::diagnostic::{self, Diagnostic};
+use interpreter::formatter;
+use interpreter::lexer;
+use interpreter::parser::{self, Tree, TreeType};
+
+/// the tree without any positions, so trees of differently formatted text can be compared
+fn shape(tree: &Tree) -> String {
+    let mut text = match &tree.value {
+        TreeType::Leaf(token) => format!("{token:?}"),
+        value => format!("{value:?}"),
+    };
+    text.push('(');
+    for param in &tree.params {
+        text.push_str(&shape(param));
+    }
+    text.push(')');
+    text
+}
+
+/// lexes and parses input, failing the test if that panics other than by reporting an error
+fn parse(input: &str) -> Result<Tree, Diagnostic> {
+    let result = diagnostic::catch(|| parser::parse(lexer::lex(input)));
+    if let Err(error) = &result {
+        assert!(error.line.is_some(), "panicked without reporting an error: {error}\ninput: {input:?}");
+    }
+    result
+}
+
+/// checks that the concrete syntax tree of input, which must parse, prints as input byte for byte
+fn check_lossless(input: &str) {
+    let output = diagnostic::catch(|| cst::parse(input).to_string())
+        .unwrap_or_else(|error| panic!("building the concrete syntax tree failed: {error}\ninput: {input:?}"));
+    assert_eq!(output, input, "the concrete syntax tree is not lossless");
+}
+
+/// formats input, which must parse, and checks that the output parses to the same tree and is
+/// already formatted
+fn check_round_trip(input: &str, tree: &Tree) {
+    let formatted = diagnostic::catch(|| formatter::format_source(input))
+        .unwrap_or_else(|error| panic!("formatting failed: {error}\ninput: {input:?}"));
+    let reparsed = parse(&formatted)
+        .unwrap_or_else(|error| panic!("formatted output does not parse: {error}\noutput: {formatted:?}"));
+    assert_eq!(shape(&reparsed), shape(tree), "formatting changed the tree\ninput: {input:?}\noutput: {formatted:?}");
+    assert_eq!(formatter::format_source(&formatted), formatted, "formatting is not idempotent");
+}
+
+const KEYWORDS: [&str; 30] = ["const", "var", "let", "int", "float", "bool", "string", "void", "enum", "struct",
+    "fn", "if", "else", "switch", "while", "for", "in", "forever", "break", "continue", "return", "defer", "try",
+    "catch", "throw", "import", "pub", "true", "false", "none"];
+
+fn identifier() -> impl Strategy<Value = String> {
+    "[a-z_][a-z0-9_]{0,5}".prop_filter("keyword", |x| !KEYWORDS.contains(&x.as_str()))
+}
+
+fn type_name() -> impl Strategy<Value = String> {
+    let simple = prop_oneof![
+        Just(String::from("int")),
+        Just(String::from("float")),
+        Just(String::from("bool")),
+        Just(String::from("string")),
+        identifier(),
+    ];
+    simple.prop_recursive(2, 8, 3, |inner| prop_oneof![
+        (prop::collection::vec(inner.clone(), 0..3), prop::option::of(inner.clone())).prop_map(|(params, ret)| match ret {
+            Some(ret) => format!("fn({}) -> {ret}", params.join(", ")),
+            None => format!("fn({})", params.join(", ")),
+        }),
+        (identifier(), prop::collection::vec(inner.clone(), 1..3)).prop_map(|(name, args)| format!("{name}<{}>", args.join(", "))),
+        // a second '?' would be lexed as '??'
+        inner.prop_map(|x| if x.ends_with('?') { x } else { format!("{x}?") }),
+    ])
+}
+
+fn expression() -> impl Strategy<Value = String> {
+    let leaf = prop_oneof![
+        (0..1_000_000i64).prop_map(|x| x.to_string()),
+        (0..4000u32).prop_map(|x| format!("{:?}", x as f64 / 8.0)),
+        any::<bool>().prop_map(|x| x.to_string()),
+        Just(String::from("none")),
+        "([a-z \t\n]|\\\\[nt\\\\\"])*".prop_map(|x| format!("\"{x}\"")),
+        identifier(),
+    ];
+    leaf.prop_recursive(6, 48, 4, |inner| prop_oneof![
+        (inner.clone(), prop::sample::select(vec!["||", "^^", "&&", "|", "^", "&", "==", "!=", "<", ">",
+            "<=", ">=", "<<", ">>", "+", "-", "*", "/", "%", "??"]), inner.clone())
+            .prop_map(|(lhs, op, rhs)| format!("{lhs} {op} {rhs}")),
+        (prop::sample::select(vec!["-", "!", "~"]), inner.clone()).prop_map(|(op, x)| format!("{op}{x}")),
+        inner.clone().prop_map(|x| format!("({x})")),
+        (identifier(), prop::collection::vec(inner.clone(), 0..4)).prop_map(|(f, args)| format!("{f}({})", args.join(", "))),
+        (prop::sample::select(vec!["\\", "fn"]), identifier(), type_name(), inner)
+            .prop_map(|(start, param, type_name, x)| format!("{start}({param}: {type_name}) -> {type_name} {{ return {x}; }}")),
+    ])
+}
+
+fn assign() -> impl Strategy<Value = String> {
+    (prop::sample::select(vec!["const", "var"]), identifier(), prop::option::of(type_name()), prop::option::of(expression()))
+        .prop_map(|(kind, name, type_name, value)| {
+            let type_name = type_name.map_or(String::new(), |x| format!(": {x}"));
+            match value {
+                Some(value) => format!("{kind} {name}{type_name} = {value};"),
+                None => format!("{kind} {name}{type_name};"),
+            }
+        })
+}
+
+fn statement() -> impl Strategy<Value = String> {
+    let simple = prop_oneof![
+        assign(),
+        (identifier(), expression()).prop_map(|(name, value)| format!("{name} = {value};")),
+        expression().prop_map(|x| format!("{x};")),
+        prop::option::of(expression()).prop_map(|x| match x {
+            Some(x) => format!("return {x};"),
+            None => String::from("return;"),
+        }),
+        expression().prop_map(|x| format!("throw {x};")),
+    ];
+    simple.prop_recursive(4, 32, 4, |inner| {
+        let body = prop::collection::vec(inner, 0..4).prop_map(|x| format!("{{\n{}\n}}", x.join("\n")));
+        prop_oneof![
+            (expression(), body.clone()).prop_map(|(cond, body)| format!("while {cond} {body}")),
+            (expression(), body.clone(), prop::option::of(body.clone()))
+                .prop_map(|(cond, body, other)| match other {
+                    Some(other) => format!("if ({cond}) {body} else {other}"),
+                    None => format!("if {cond} {body}"),
+                }),
+            (identifier(), expression(), body.clone()).prop_map(|(name, value, body)| format!("if let {name} = {value} {body}")),
+            (body.clone(), identifier(), body).prop_map(|(body, name, catch)| format!("try {body} catch {name} {catch}")),
+        ]
+    })
+}
+
+/// type parameters of a generic declaration, or nothing
+fn type_parameters() -> impl Strategy<Value = String> {
+    prop::collection::vec(identifier(), 0..3).prop_map(|x| match x.is_empty() {
+        true => String::new(),
+        false => format!("<{}>", x.join(", ")),
+    })
+}
+
+fn function() -> impl Strategy<Value = String> {
+    let param = (identifier(), type_name()).prop_map(|(name, type_name)| format!("{name}: {type_name}"));
+    (identifier(), type_parameters(), prop::collection::vec(param, 0..4), prop::option::of(type_name()),
+        prop::collection::vec(statement(), 0..5))
+        .prop_map(|(name, type_params, params, ret, body)| {
+            let ret = ret.map_or(String::new(), |x| format!(" -> {x}"));
+            format!("fn {name}{type_params}({}){ret} {{\n{}\n}}", params.join(", "), body.join("\n"))
+        })
+}
+
+fn structure() -> impl Strategy<Value = String> {
+    let field = (identifier(), type_name()).prop_map(|(name, type_name)| format!("{name}: {type_name},"));
+    (identifier(), type_parameters(), prop::collection::vec(field, 0..4))
+        .prop_map(|(name, type_params, fields)| format!("struct {name}{type_params} {{\n{}\n}}", fields.join("\n")))
+}
+
+/// a whole file of valid syntax
+fn program() -> impl Strategy<Value = String> {
+    let item = prop_oneof![function(), structure(), statement()];
+    let comment = "// [a-z ]{0,10}\n";
+    prop::collection::vec((item, prop::option::of(comment)), 0..6).prop_map(|items| {
+        items.into_iter().map(|(item, comment)| item + "\n" + &comment.unwrap_or_default()).collect()
+    })
+}
+
+/// text made up mostly of pieces of valid syntax in no particular order
+fn token_soup() -> impl Strategy<Value = String> {
+    let piece = prop::sample::select(vec!["(", ")", "{", "}", "[", "]", ",", ".", ":", ";", "->", "=", "+", "-",
+        "*", "/", "%", "~", "|", "&", "^", "!", "||", "&&", "^^", "==", "!=", "<", ">", "<=", ">=", "<<", ">>", "const",
+        "var", "int", "float", "bool", "string", "void", "enum", "struct", "fn", "if", "else", "switch", "while",
+        "return", "let", "none", "try", "catch", "throw", "?", "??", "true", "1", "2.5", "\"s\"", "x", "f", "\n", "//", "\"", "\\"]);
+    prop::collection::vec((piece, prop::sample::select(vec![" ", "", "\n"])), 0..64)
+        .prop_map(|x| x.into_iter().map(|(piece, space)| format!("{piece}{space}")).collect())
+}
+
+proptest! {
+    #[test]
+    fn random_text_never_crashes(input in any::<String>()) {
+        if let Ok(tree) = parse(&input) {
+            check_lossless(&input);
+            check_round_trip(&input, &tree);
+        }
+    }
+
+    #[test]
+    fn token_soup_never_crashes(input in token_soup()) {
+        if let Ok(tree) = parse(&input) {
+            check_lossless(&input);
+            check_round_trip(&input, &tree);
+        }
+    }
+
+    #[test]
+    fn programs_round_trip(input in program()) {
+        let tree = parse(&input).unwrap_or_else(|error| panic!("generated program does not parse: {error}\ninput: {input:?}"));
+        check_lossless(&input);
+        check_round_trip(&input, &tree);
+    }
+}
+
+#[test]
+fn sample_files_are_lossless() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("files");
+    let mut checked = 0;
+    for entry in std::fs::read_dir(dir.join("modules")).unwrap().chain(std::fs::read_dir(&dir).unwrap()) {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|x| x != "in") {
+            continue;
+        }
+        let input = std::fs::read_to_string(&path).unwrap();
+        // a few of the samples are there to show off errors, and have no tree to check
+        if parse(&input).is_ok() {
+            check_lossless(&input);
+            checked += 1;
+        }
+    }
+    assert!(checked > 10, "only {checked} sample files parsed");
+}
+
+#[test]
+fn deep_nesting_is_an_error() {
+    for input in ["(".repeat(100_000), "-".repeat(100_000), "fn f() ".to_string() + &"{".repeat(100_000)] {
+        assert!(parse(&input).is_err());
+    }
+}
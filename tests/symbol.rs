@@ -0,0 +1,23 @@
+use interpreter::symbol::Symbol;
+
+#[test]
+fn interned_strings_read_back() {
+    // enough names to fill several chunks of the interner
+    let names: Vec<String> = (0..5000).map(|i| format!("symbol_test_{i}")).collect();
+    let symbols: Vec<Symbol> = names.iter().map(|x| Symbol::intern(x)).collect();
+    for (name, symbol) in names.iter().zip(&symbols) {
+        assert_eq!(symbol.as_str(), name);
+        assert_eq!(Symbol::intern(name), *symbol);
+    }
+    // symbols made on one thread can be read on another
+    let handle = std::thread::spawn(move || symbols.iter().map(|x| x.to_string()).collect::<Vec<_>>());
+    assert_eq!(handle.join().unwrap(), names);
+}
+
+#[test]
+fn lookup_does_not_intern() {
+    assert_eq!(Symbol::lookup("symbol_test_never_interned"), None);
+    assert_eq!(Symbol::lookup("symbol_test_never_interned"), None);
+    let symbol = Symbol::intern("symbol_test_looked_up");
+    assert_eq!(Symbol::lookup("symbol_test_looked_up"), Some(symbol));
+}